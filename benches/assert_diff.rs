@@ -0,0 +1,100 @@
+//! Benchmark hashing a synthetic large tree with `assert-diff`, sequentially and in parallel.
+//!
+//! Run with `cargo bench --bench assert_diff`. The size of the tree can be changed with
+//! `DEVOBS_BENCH_FILES` (number of files) and `DEVOBS_BENCH_FILE_SIZE` (bytes per file), and the
+//! number of threads to compare with a single thread with `DEVOBS_BENCH_JOBS` (defaults to the
+//! number of available CPUs).
+use std::{env,
+          fs::{create_dir_all, write},
+          path::Path,
+          process::Command,
+          time::{Duration, Instant}};
+
+use tempfile::tempdir;
+
+const DEFAULT_FILES: usize = 20_000;
+const DEFAULT_FILE_SIZE: usize = 16 * 1024;
+const FILES_PER_DIR: usize = 100;
+const ITERATIONS: usize = 3;
+
+fn main() {
+    let args = env::args().collect::<Vec<_>>();
+
+    // Benchmarks are also run as tests by `cargo test --all-targets`, without `--bench`;
+    // list no tests, or just check the benchmark works on a small tree
+    if args.iter().any(|arg| arg == "--list") {
+        return;
+    }
+    let (files, file_size) = if args.iter().any(|arg| arg == "--bench") {
+        (
+            env_or("DEVOBS_BENCH_FILES", DEFAULT_FILES),
+            env_or("DEVOBS_BENCH_FILE_SIZE", DEFAULT_FILE_SIZE),
+        )
+    } else {
+        (100, 1024)
+    };
+
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let target = temp_dir.path().join("target");
+    println!("Generating {files} files of {file_size} bytes...");
+    generate_tree(&target, files, file_size);
+
+    let parallelism = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut results = vec![];
+    for jobs in [1, env_or("DEVOBS_BENCH_JOBS", parallelism)] {
+        let elapsed = (0..ITERATIONS)
+            .map(|_| run_assert_diff(&target, jobs))
+            .min()
+            .expect("No iterations");
+        println!("assert-diff --jobs {jobs:>3}: {elapsed:>10.3?} (best of {ITERATIONS})");
+        results.push(elapsed);
+    }
+    println!(
+        "Speedup: {:.2}x",
+        results[0].as_secs_f64() / results[1].as_secs_f64()
+    );
+}
+
+/// Read a number from the environment variable, or use the default.
+fn env_or(name: &str, default: usize) -> usize {
+    env::var(name)
+        .ok()
+        .map(|value| value.parse().expect("Invalid number"))
+        .unwrap_or(default)
+}
+
+/// Generate files with distinct pseudo-random contents, spread over nested directories.
+fn generate_tree(target: &Path, files: usize, file_size: usize) {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    for i in 0..files {
+        let dir = target.join(format!(
+            "{:03}/{:03}",
+            i / (FILES_PER_DIR * FILES_PER_DIR),
+            i / FILES_PER_DIR % FILES_PER_DIR
+        ));
+        create_dir_all(&dir).expect("Failed to create directory");
+        let content = (0..file_size)
+            .map(|_| {
+                // xorshift64
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                b'a' + (state % 26) as u8
+            })
+            .collect::<Vec<_>>();
+        write(dir.join(format!("file{i}.txt")), content).expect("Failed to write file");
+    }
+}
+
+/// Run `assert-diff` with a no-op command, returning the elapsed time.
+fn run_assert_diff(target: &Path, jobs: usize) -> Duration {
+    let start = Instant::now();
+    let status = Command::new(env!("CARGO_BIN_EXE_devobs"))
+        .args(["--log-level", "error", "assert-diff", "--target"])
+        .arg(target)
+        .args(["--jobs", &jobs.to_string(), "--", "true"])
+        .status()
+        .expect("Failed to run devobs");
+    assert!(status.success(), "assert-diff failed");
+    start.elapsed()
+}
@@ -1,2 +1,3 @@
 pub(crate) mod assert_diff;
 pub(crate) mod check_file_pair;
+pub(crate) mod snapshot;
@@ -1,15 +1,42 @@
-use std::{fs::File,
-          hash::{DefaultHasher, Hash, Hasher},
-          io::Read,
-          path::{Path, PathBuf, absolute}};
+use std::{collections::BTreeMap,
+          env::current_dir,
+          fs::{create_dir_all, read, remove_dir, remove_file, symlink_metadata, write},
+          num::NonZeroUsize,
+          path::{MAIN_SEPARATOR, Path, PathBuf, absolute},
+          process::{Child, ExitStatus},
+          slice::from_ref,
+          thread::sleep,
+          time::{Duration, Instant}};
 
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, anyhow, bail};
 use clap::{Args, ValueEnum};
 use serde::Serialize;
+use serde_json::Value;
+use tempfile::TempDir;
 
-use crate::{GlobalOpts, utils::fs::list_files};
+use crate::{GlobalOpts,
+            utils::{diff::{Content, unified_diff},
+                    duration::parse_duration,
+                    fs::{common_ancestor, copy_dir, copy_file, matches_globs},
+                    git::{Baseline, changed_files},
+                    hash::HashAlgorithm,
+                    manifest::{Changes, DirectoryHash, HashOptions, Track,
+                               calculate_directory_hash},
+                    normalize::{IgnoreLine, Normalization},
+                    semantic::{describe_changes, parse},
+                    target::TargetSpec}};
 
-const BUFFER_SIZE: usize = 8192;
+/// Exit code when the command is killed after timeout, same as `timeout` of GNU coreutils.
+const TIMEOUT_EXIT_CODE: i32 = 124;
+
+/// Interval to check whether the command has exited, when a timeout is set.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Contents of files in the target directory, keyed by relative path.
+type Contents = BTreeMap<PathBuf, Content>;
+
+/// Parsed values of files compared semantically, keyed by relative path.
+type Values = BTreeMap<PathBuf, Value>;
 
 #[derive(ValueEnum, Clone, Debug, Serialize, Default)]
 #[serde(rename_all = "kebab-case")]
@@ -22,27 +49,183 @@ enum OnCommandError {
     Ignore,
 }
 
-// NOTE: This command does not support dry-run mode, as there is no state change involved (except hash file).
+// NOTE: Dry-run mode only affects `--apply` and `--restore-on-change`, as there is no other state change involved
+//       (except hash file).
 /// Detects changes in the target directory by comparing file hashes before and after running a command.
 /// Raises an error if any changes are detected.
 #[derive(Args, Debug, Clone)]
 pub(crate) struct CommandArgs {
-    /// Target directory to watch for changes.
-    #[arg(long)]
-    target: String,
+    /// Target directory to watch for changes, optionally with its own include and exclude
+    /// patterns as `PATH;include=GLOB,...;exclude=GLOB,...`, which take precedence over
+    /// `--include` and `--exclude`.
+    ///
+    /// This option can be specified multiple times to watch several targets, each hashed
+    /// independently with changes reported per target.
+    #[arg(long, required = true)]
+    target: Vec<TargetSpec>,
 
-    /// List of glob patterns to include files from the `target` directory.
+    /// List of glob patterns to include files from the target directories, unless given for
+    /// the target.
     ///
     /// This option can be specified multiple times or as a comma-separated list.
     #[arg(long, num_args = 1.., value_delimiter = ',', default_value = "**/*")]
     include: Vec<String>,
 
-    /// List of glob patterns to exclude files from the `target` directory.
+    /// List of glob patterns to exclude files from the target directories, unless given for
+    /// the target.
     ///
     /// This option can be specified multiple times or as a comma-separated list.
     #[arg(long, num_args = 1.., value_delimiter = ',')]
     exclude: Vec<String>,
 
+    /// List of glob patterns of files allowed to change. Changes to matching files are reported,
+    /// but do not fail the command.
+    ///
+    /// This option can be specified multiple times or as a comma-separated list.
+    #[arg(long, num_args = 1.., value_delimiter = ',')]
+    allow_changed: Vec<String>,
+
+    /// List of glob patterns of files expected to change, such as `migrations/*`. Fails if no
+    /// file matching any of the patterns has changed, in any target. Changes to matching files
+    /// are allowed.
+    ///
+    /// This option can be specified multiple times or as a comma-separated list.
+    #[arg(long, num_args = 1.., value_delimiter = ',')]
+    expect_changed: Vec<String>,
+
+    /// Additional attributes to track for changes, besides file paths and contents.
+    ///
+    /// This option can be specified multiple times or as a comma-separated list.
+    #[arg(long, num_args = 1.., value_delimiter = ',', value_enum)]
+    track: Vec<Track>,
+
+    /// Algorithm used to hash file contents. Digests are stable across versions and platforms.
+    #[arg(long, default_value_t, value_enum)]
+    hash_algorithm: HashAlgorithm,
+
+    /// Number of threads to hash files with. Defaults to the number of available CPUs.
+    /// The result is the same regardless of the number of threads.
+    #[arg(long)]
+    jobs: Option<NonZeroUsize>,
+
+    /// List of glob patterns of files to convert CRLF line endings to LF in before hashing.
+    ///
+    /// This option can be specified multiple times or as a comma-separated list.
+    #[arg(long, num_args = 1.., value_delimiter = ',')]
+    normalize_eol: Vec<String>,
+
+    /// List of glob patterns of files to strip trailing whitespace of lines in before hashing.
+    ///
+    /// This option can be specified multiple times or as a comma-separated list.
+    #[arg(long, num_args = 1.., value_delimiter = ',')]
+    strip_trailing_whitespace: Vec<String>,
+
+    /// Lines to drop from files before hashing, as `GLOB=REGEX`, such as
+    /// `**/*.ts=^// Generated at `.
+    ///
+    /// This option can be specified multiple times.
+    #[arg(long)]
+    ignore_line_regex: Vec<IgnoreLine>,
+
+    /// List of glob patterns of JSON, YAML or TOML files to compare by their parsed values,
+    /// ignoring key order and formatting. Changed values are reported with paths of their keys.
+    /// The format is detected from the file extension.
+    ///
+    /// This option can be specified multiple times or as a comma-separated list.
+    #[arg(long, num_args = 1.., value_delimiter = ',')]
+    semantic: Vec<String>,
+
+    /// Show unified diffs of changed text files. Contents of files are kept in memory
+    /// before running the command, up to `--diff-max-file-size` bytes per file.
+    #[arg(long, default_value_t = false)]
+    show_diff: bool,
+
+    /// Write the unified diffs of changed text files to the patch file, which can be applied
+    /// in the target directory with `git apply` or `patch -p1`. Binary files and files too large
+    /// to diff are left out.
+    ///
+    /// With multiple targets, paths are prefixed with the targets as given, so the patch can be
+    /// applied in the current directory if the targets are relative paths.
+    #[arg(long)]
+    patch_out: Option<PathBuf>,
+
+    /// Maximum size of a file in bytes to keep its contents for diffs.
+    #[arg(long, default_value_t = 1024 * 1024)]
+    diff_max_file_size: u64,
+
+    /// Maximum number of diff lines to show, the rest is truncated. The patch file is never
+    /// truncated.
+    #[arg(long, default_value_t = 1000)]
+    diff_max_lines: usize,
+
+    /// Compare the working tree with a baseline in the git repository after running the command,
+    /// instead of with the target before running it: `git-index` for contents staged in the index,
+    /// or a commit, branch or tag such as `HEAD`.
+    ///
+    /// Untracked files not ignored by git are reported as added.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "track",
+            "show_diff",
+            "patch_out",
+            "normalize_eol",
+            "strip_trailing_whitespace",
+            "ignore_line_regex",
+            "semantic",
+        ]
+    )]
+    against: Option<Baseline>,
+
+    /// Run the command in a copy of the target in a temporary directory, leaving the target
+    /// untouched. Changes are detected by comparing the copy with the target.
+    #[arg(long, default_value_t = false, conflicts_with = "against")]
+    sandbox: bool,
+
+    /// Directory to copy into the sandbox instead of the target, such as the project root,
+    /// when the command needs files outside of the target. Must contain the targets and the
+    /// working directory. Defaults to the deepest directory containing both.
+    ///
+    /// The command runs in the copy of the working directory.
+    #[arg(long, requires = "sandbox")]
+    sandbox_root: Option<PathBuf>,
+
+    /// Copy changes made in the sandbox back to the target. The command still fails, as changes
+    /// were detected. Changes are not copied if the command fails or times out.
+    #[arg(long, default_value_t = false, requires = "sandbox")]
+    apply: bool,
+
+    /// Back up files in the target before running the command, and restore them if the check
+    /// fails, such as on unexpected changes or an error of the command, removing files created by
+    /// the command. Changes are still reported, and allowed changes are kept if the check passes.
+    #[arg(long, default_value_t = false, conflicts_with_all = ["against", "sandbox"])]
+    restore_on_change: bool,
+
+    /// Kill the command if it does not exit within the duration, such as `30s`, `500ms` or `5m`,
+    /// and exit with code 124. On Unix, processes started by the command are killed as well, as
+    /// the command runs in its own process group, which does not receive signals from the
+    /// terminal such as Ctrl-C.
+    #[arg(long, value_parser = parse_duration)]
+    timeout: Option<Duration>,
+
+    /// Environment variable to set for the command, as `KEY=VALUE`.
+    ///
+    /// This option can be specified multiple times.
+    #[arg(long, value_parser = parse_env)]
+    env: Vec<(String, String)>,
+
+    /// Working directory to run the command in. Defaults to the current directory.
+    ///
+    /// With `--sandbox`, the command runs in the copy of this directory, which must be within
+    /// the sandbox root.
+    #[arg(long)]
+    cwd: Option<PathBuf>,
+
+    /// Run the command with the shell (`sh -c`, or `cmd /C` on Windows), joining its arguments
+    /// with spaces, to support shell syntax such as `make gen && cargo fmt`.
+    #[arg(long, default_value_t = false)]
+    shell: bool,
+
     /// Error handling strategy for the command.
     #[arg(long, default_value_t, value_enum)]
     on_command_error: OnCommandError,
@@ -53,28 +236,641 @@ pub(crate) struct CommandArgs {
     command: Vec<String>,
 }
 
-pub(crate) fn command(args: CommandArgs, _global_opts: GlobalOpts) -> Result<()> {
+pub(crate) fn command(args: CommandArgs, global_opts: GlobalOpts) -> Result<()> {
     // Prepare arguments
-    let target = absolute(PathBuf::from(&args.target))?;
-    if !target.exists() {
-        bail!("Target path does not exist: {}", target.display());
+    let mut targets = args
+        .target
+        .iter()
+        .map(|spec| Target::new(spec, &args.include, &args.exclude))
+        .collect::<Result<Vec<_>>>()?;
+    if targets.len() > 1 {
+        targets
+            .iter_mut()
+            .for_each(|target| target.qualified = true);
     }
     if args.command.is_empty() {
         bail!("No command specified to run.");
     }
 
-    // Calculate hash
-    log::debug!("Calculating hash for: {}", target.display());
-    let before_hash = calculate_directory_hash(&target, &args.include, &args.exclude)?;
-    log::info!("Hash before command run: {}", before_hash);
+    let cwd = args.cwd.as_deref().map(absolute).transpose()?;
+    if let Some(cwd) = &cwd
+        && !cwd.is_dir()
+    {
+        bail!("Working directory does not exist: {}", cwd.display());
+    }
+
+    let mut expectations = Expectations::new(&args.allow_changed, &args.expect_changed);
+
+    // Compare with git baseline instead of hashes, if requested
+    if let Some(against) = &args.against {
+        // Changes are still reported if the command fails or times out
+        let exit_code = run_command(&args, cwd.as_deref());
+        let mut changed = vec![];
+        for target in &targets {
+            let changes = changed_files(&target.path, against, target.include, target.exclude)?;
+            let (allowed, unexpected) = expectations.check(&changes)?;
+            if !allowed.is_empty() {
+                log::warn!("Allowed changes{}:{allowed}", target.for_target());
+            }
+            if !unexpected.is_empty() {
+                changed.push((target.name, unexpected.to_string()));
+            }
+        }
+        let result = expectations.verify(
+            &format!("Working tree differs from {against} after running command"),
+            &changed,
+            targets.len(),
+        );
+        if let Some(code) = exit_code? {
+            exit_with(code, result);
+        }
+        result?;
+        if expectations.any_allowed {
+            log::info!("Working tree matches {against}, except for allowed changes.");
+        } else {
+            log::info!("Working tree matches {against}, no changes detected.");
+        }
+        return Ok(());
+    }
+
+    let normalization = Normalization {
+        eol: args.normalize_eol.clone(),
+        trailing_whitespace: args.strip_trailing_whitespace.clone(),
+        ignore_lines: args.ignore_line_regex.clone(),
+        semantic: args.semantic.clone(),
+    };
+
+    // Calculate hashes, keeping contents of files to show diffs of them later, values of files
+    // compared semantically to tell changed keys and backing up files to restore them if changed
+    let keep_contents = args.show_diff || args.patch_out.is_some();
+    let mut befores = vec![];
+    for target in &targets {
+        log::debug!("Calculating hash for: {}", target.path.display());
+        let before =
+            calculate_directory_hash(&target.path, &target.hash_options(&args, &normalization))?;
+        log::info!(
+            "Hash before command run{}: {}",
+            target.for_target(),
+            before.hash
+        );
+        let contents = if keep_contents {
+            read_contents(&target.path, before.files.keys(), args.diff_max_file_size)?
+        } else {
+            Contents::new()
+        };
+        let values = read_values(&target.path, before.files.keys(), &args.semantic)?;
+        let backup = if args.restore_on_change {
+            Some(backup_files(&target.path, &before)?)
+        } else {
+            None
+        };
+        befores.push((before, contents, values, backup));
+    }
+
+    // Copy the project into a sandbox, so the command leaves the targets untouched
+    let sandbox = if args.sandbox {
+        Some(Sandbox::new(
+            targets.iter().map(|target| target.path.as_path()),
+            args.sandbox_root.as_deref(),
+            cwd.as_deref(),
+        )?)
+    } else {
+        None
+    };
+    let command_cwd = match &sandbox {
+        Some(sandbox) => Some(sandbox.cwd.as_path()),
+        None => cwd.as_deref(),
+    };
+
+    // Changes are still checked if the command fails or times out, to restore the target
+    let exit_code = run_command(&args, command_cwd);
+
+    let mut patch = String::new();
+    let mut report = String::new();
+    let mut changed = vec![];
+    let mut restores = vec![];
+    for (target, (before, before_contents, before_values, backup)) in targets.iter().zip(&befores) {
+        let changed_target = match &sandbox {
+            Some(sandbox) => sandbox.path(&target.path),
+            None => target.path.clone(),
+        };
+
+        // Calculate hash again
+        let after =
+            calculate_directory_hash(&changed_target, &target.hash_options(&args, &normalization))?;
+        log::info!(
+            "Hash after command run{}: {}",
+            target.for_target(),
+            after.hash
+        );
+
+        // Compare hashes
+        let mut changes = Changes::between(before, &after);
+        let after_values = read_values(&changed_target, &changes.modified, &args.semantic)?;
+        for (path, after_value) in &after_values {
+            if let Some(before_value) = before_values.get(path) {
+                changes
+                    .details
+                    .entry(path.clone())
+                    .or_default()
+                    .extend(describe_changes(before_value, after_value));
+            }
+        }
+        if !changes.normalized.is_empty() {
+            log::info!(
+                "Differences disappeared after normalization{}:{}",
+                target.for_target(),
+                changes
+                    .normalized
+                    .iter()
+                    .map(|path| format!("\n  {}", path.display()))
+                    .collect::<String>()
+            );
+        }
+        if keep_contents {
+            let after_contents =
+                read_contents(&changed_target, changes.paths(), args.diff_max_file_size)?;
+            let prefix = target.qualified.then_some(target.name);
+            let (target_patch, target_report) =
+                render_diffs(&changes, before_contents, &after_contents, prefix);
+            patch.push_str(&target_patch);
+            report.push_str(&target_report);
+        }
+        if args.apply && matches!(exit_code, Ok(None)) && !changes.is_empty() {
+            log::warn!(
+                "Applying changes made in the sandbox to the target{}",
+                target.named()
+            );
+            if !global_opts.dry_run {
+                apply_changes(&changes, &changed_target, &target.path)?;
+            }
+        }
+        if let Some(backup) = backup
+            && !changes.is_empty()
+        {
+            restores.push((target, backup, changes.reversed()));
+        }
+        let (allowed, unexpected) = expectations.check(&changes)?;
+        if !allowed.is_empty() {
+            log::warn!("Allowed changes{}:{allowed}", target.for_target());
+        }
+        if !unexpected.is_empty() {
+            changed.push((
+                target.name,
+                format!(" {} != {}{}", before.hash, after.hash, unexpected),
+            ));
+        }
+    }
+    if args.show_diff && !report.is_empty() {
+        log::warn!(
+            "Diff of changed files:\n{}",
+            truncate_lines(&report, args.diff_max_lines)
+        );
+    }
+    if let Some(patch_out) = &args.patch_out {
+        log::info!("Writing patch of changed files to: {}", patch_out.display());
+        write(patch_out, patch)
+            .with_context(|| format!("Failed to write patch file: {}", patch_out.display()))?;
+    }
+    let result = expectations.verify(
+        "Hash has changed after running command",
+        &changed,
+        targets.len(),
+    );
+
+    // Restore targets only if the check fails, so allowed changes are kept otherwise
+    if result.is_err() || !matches!(exit_code, Ok(None)) {
+        for (target, backup, changes) in restores {
+            log::warn!(
+                "Restoring the target{} to its state before running the command",
+                target.named()
+            );
+            if !global_opts.dry_run {
+                apply_changes(&changes, backup.path(), &target.path)?;
+            }
+        }
+    }
+    if let Some(code) = exit_code? {
+        // Clean up temporary directories before exiting
+        drop(befores);
+        drop(sandbox);
+        exit_with(code, result);
+    }
+    result?;
+
+    // No changes detected
+    if expectations.any_allowed {
+        log::info!("Target hash changed, but only with allowed changes.");
+    } else {
+        log::info!("Target hash matches, no changes detected.");
+    }
+    Ok(())
+}
+
+/// Target directory to watch, resolved from its spec.
+struct Target<'a> {
+    /// Path of the target as given, to report changes with.
+    name: &'a str,
+
+    /// Absolute path of the target.
+    path: PathBuf,
+
+    /// Patterns to include files from the target.
+    include: &'a [String],
+
+    /// Patterns to exclude files from the target.
+    exclude: &'a [String],
+
+    /// Whether there are other targets, so log messages mention which target they are about.
+    qualified: bool,
+}
+
+impl<'a> Target<'a> {
+    fn new(spec: &'a TargetSpec, include: &'a [String], exclude: &'a [String]) -> Result<Self> {
+        let path = absolute(PathBuf::from(&spec.path))?;
+        if !path.exists() {
+            bail!("Target path does not exist: {}", path.display());
+        }
+        Ok(Self {
+            name: &spec.path,
+            path,
+            include: spec.include(include),
+            exclude: spec.exclude(exclude),
+            qualified: false,
+        })
+    }
+
+    /// Options to hash files in the target with.
+    fn hash_options<'b>(
+        &'b self,
+        args: &'b CommandArgs,
+        normalization: &'b Normalization,
+    ) -> HashOptions<'b> {
+        HashOptions {
+            include: self.include,
+            exclude: self.exclude,
+            track: &args.track,
+            algorithm: args.hash_algorithm,
+            jobs: args.jobs,
+            normalization,
+        }
+    }
+
+    /// Suffix to tell the target in log messages, such as `` for `docs/api` ``.
+    fn for_target(&self) -> String {
+        if self.qualified {
+            format!(" for `{}`", self.name)
+        } else {
+            String::new()
+        }
+    }
+
+    /// Name of the target to tell it in log messages, such as `` `docs/api` ``, with leading
+    /// space.
+    fn named(&self) -> String {
+        if self.qualified {
+            format!(" `{}`", self.name)
+        } else {
+            String::new()
+        }
+    }
+}
+
+/// Changes allowed or expected with `--allow-changed` and `--expect-changed`.
+struct Expectations {
+    /// Patterns of files allowed to change, including ones expected to change.
+    allowed: Vec<String>,
+
+    /// Patterns of files expected to change, with whether any matching change is found.
+    expected: Vec<(String, bool)>,
+
+    /// Whether any allowed change is found.
+    any_allowed: bool,
+}
+
+impl Expectations {
+    fn new(allow_changed: &[String], expect_changed: &[String]) -> Self {
+        Self {
+            allowed: [allow_changed, expect_changed].concat(),
+            expected: expect_changed
+                .iter()
+                .map(|pattern| (pattern.clone(), false))
+                .collect(),
+            any_allowed: false,
+        }
+    }
+
+    /// Split changes of a target into allowed and unexpected ones, noting which expected
+    /// patterns are matched.
+    fn check(&mut self, changes: &Changes) -> Result<(Changes, Changes)> {
+        for (pattern, found) in &mut self.expected {
+            for path in changes.paths() {
+                *found = *found || matches_globs(path, from_ref(pattern), &[])?;
+            }
+        }
+        let (allowed, unexpected) = changes.partition(&self.allowed)?;
+        self.any_allowed = self.any_allowed || !allowed.is_empty();
+        Ok((allowed, unexpected))
+    }
+
+    /// Fail if there are unexpected changes in targets, given as their names with details, or
+    /// if no change matches some expected pattern.
+    fn verify(&self, headline: &str, changed: &[(&str, String)], total: usize) -> Result<()> {
+        let mut errors = vec![];
+        if !changed.is_empty() {
+            errors.push(changes_message(headline, changed, total));
+        }
+        let missing = self
+            .expected
+            .iter()
+            .filter(|(_, found)| !found)
+            .map(|(pattern, _)| format!("`{pattern}`"))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            errors.push(format!(
+                "No changes matching expected patterns after running command: {}",
+                missing.join(", ")
+            ));
+        }
+        if !errors.is_empty() {
+            bail!("{}", errors.join("\n"));
+        }
+        Ok(())
+    }
+}
+
+/// Message of changes detected in targets, given as their names with details. Details are
+/// grouped by target if there are multiple targets.
+fn changes_message(headline: &str, changed: &[(&str, String)], total: usize) -> String {
+    if total == 1 {
+        return format!("{headline}:{}", changed[0].1);
+    }
+    let mut message = format!("{headline} in {} of {total} targets:", changed.len());
+    for (name, details) in changed {
+        message.push_str(&format!("\n  {name}:{}", details.replace('\n', "\n  ")));
+    }
+    message
+}
+
+/// Copy of the project in a temporary directory, to run the command in.
+struct Sandbox {
+    /// Temporary directory, removed when dropped.
+    dir: TempDir,
+
+    /// Directory copied into the sandbox.
+    root: PathBuf,
+
+    /// Working directory to run the command in.
+    cwd: PathBuf,
+}
+
+impl Sandbox {
+    /// Copy the root directory, defaulting to the deepest directory containing all targets and
+    /// the working directory, into a new temporary directory. The working directory defaults to
+    /// the current directory, and must be within the root.
+    fn new<'a>(
+        targets: impl Iterator<Item = &'a Path> + Clone,
+        root: Option<&Path>,
+        cwd: Option<&Path>,
+    ) -> Result<Self> {
+        let cwd = match cwd {
+            Some(cwd) => cwd.to_path_buf(),
+            None => absolute(current_dir()?)?,
+        };
+        let root = match root {
+            Some(root) => absolute(root)?,
+            None => {
+                let mut paths = targets.clone().collect::<Vec<&Path>>();
+                paths.push(&cwd);
+                common_ancestor(paths).context(
+                    "Targets and the working directory have no common directory to copy into \
+                     the sandbox",
+                )?
+            }
+        };
+        for target in targets {
+            if !target.starts_with(&root) {
+                bail!(
+                    "Target {} is not within the sandbox root {}",
+                    target.display(),
+                    root.display()
+                );
+            }
+        }
+        let Ok(relative_cwd) = cwd.strip_prefix(&root) else {
+            bail!(
+                "Working directory {} is not within the sandbox root {}, specify \
+                 `--sandbox-root` containing both",
+                cwd.display(),
+                root.display()
+            );
+        };
 
-    // Run command
+        let dir = tempfile::Builder::new()
+            .prefix("devobs-sandbox-")
+            .tempdir()?;
+        log::info!("Copying {} into sandbox", root.display());
+        log::debug!("Sandbox directory: {}", dir.path().display());
+        copy_dir(&root, dir.path())?;
+
+        let cwd = dir.path().join(relative_cwd);
+        Ok(Self { dir, root, cwd })
+    }
+
+    /// Path of the copy of the given path within the root.
+    fn path(&self, path: &Path) -> PathBuf {
+        let relative_path = path
+            .strip_prefix(&self.root)
+            .expect("Path should be within the sandbox root");
+        self.dir.path().join(relative_path)
+    }
+}
+
+/// Copy files in the target into a temporary directory, to restore them later.
+fn backup_files(target: &Path, directory: &DirectoryHash) -> Result<TempDir> {
+    let backup = tempfile::Builder::new()
+        .prefix("devobs-backup-")
+        .tempdir()?;
+    log::info!("Backing up {} files in target", directory.files.len());
+    log::debug!("Backup directory: {}", backup.path().display());
+    for path in directory.files.keys() {
+        let (source, destination) = (target.join(path), backup.path().join(path));
+        if symlink_metadata(&source)?.is_dir() {
+            create_dir_all(&destination)?;
+        } else {
+            copy_file(&source, &destination)?;
+        }
+    }
+    Ok(backup)
+}
+
+/// Apply changes of files from the changed directory to the target directory, by copying added
+/// and modified files and removing removed ones.
+fn apply_changes(changes: &Changes, from: &Path, to: &Path) -> Result<()> {
+    for path in changes.added.iter().chain(&changes.modified) {
+        let (source, destination) = (from.join(path), to.join(path));
+        log::debug!("Copying {} to {}", source.display(), destination.display());
+        if symlink_metadata(&source)?.is_dir() {
+            create_dir_all(&destination)?;
+        } else {
+            copy_file(&source, &destination)?;
+        }
+    }
+    for path in &changes.removed {
+        let destination = to.join(path);
+        log::debug!("Removing {}", destination.display());
+        if symlink_metadata(&destination)?.is_dir() {
+            remove_dir(&destination)?;
+        } else {
+            remove_file(&destination)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read contents of the files at the given paths relative to the target. Paths which are not
+/// files, such as tracked directories, are skipped.
+fn read_contents<'a>(
+    target: &Path,
+    paths: impl IntoIterator<Item = &'a PathBuf>,
+    max_size: u64,
+) -> Result<Contents> {
+    let mut contents = Contents::new();
+    for path in paths {
+        let full_path = target.join(path);
+        if full_path.is_file() {
+            contents.insert(path.clone(), Content::read(&full_path, max_size)?);
+        }
+    }
+    Ok(contents)
+}
+
+/// Parse the files at the given paths relative to the target which match any of the patterns, to
+/// compare them semantically. Files which fail to parse are skipped, as they are compared by
+/// contents instead.
+fn read_values<'a>(
+    target: &Path,
+    paths: impl IntoIterator<Item = &'a PathBuf>,
+    patterns: &[String],
+) -> Result<Values> {
+    let mut values = Values::new();
+    if patterns.is_empty() {
+        return Ok(values);
+    }
+    for path in paths {
+        let full_path = target.join(path);
+        if !full_path.is_file() || !matches_globs(path, patterns, &[])? {
+            continue;
+        }
+        match parse(path, &read(&full_path)?) {
+            Ok(value) => {
+                values.insert(path.clone(), value);
+            }
+            Err(err) => log::debug!("Skipping file failed to parse: {}: {err}", path.display()),
+        }
+    }
+    Ok(values)
+}
+
+/// Render diffs of changed files, returning the patch of text files and the report to show,
+/// which also mentions files that cannot be diffed. Paths are prefixed with the prefix, if given.
+fn render_diffs(
+    changes: &Changes,
+    before: &Contents,
+    after: &Contents,
+    prefix: Option<&str>,
+) -> (String, String) {
+    let mut patch = String::new();
+    let mut report = String::new();
+    for path in changes.paths() {
+        let mut display_path = path.to_string_lossy().replace(MAIN_SEPARATOR, "/");
+        if let Some(prefix) = prefix {
+            let prefix = prefix.replace(MAIN_SEPARATOR, "/");
+            let prefix = prefix.trim_start_matches("./").trim_end_matches('/');
+            display_path = format!("{prefix}/{display_path}");
+        }
+        match (before.get(path), after.get(path)) {
+            // Tracked directories
+            (None, None) => {}
+            (Some(Content::Binary), _) | (_, Some(Content::Binary)) => {
+                log::warn!("Not including binary file in the patch: {display_path}");
+                report.push_str(&format!("Binary file {display_path} differs\n"));
+            }
+            (Some(Content::TooLarge), _) | (_, Some(Content::TooLarge)) => {
+                log::warn!("Not including file too large to diff in the patch: {display_path}");
+                report.push_str(&format!("File {display_path} is too large to diff\n"));
+            }
+            (before, after) => {
+                let diff = unified_diff(&display_path, text(before), text(after));
+                patch.push_str(&diff);
+                report.push_str(&diff);
+            }
+        }
+    }
+    (patch, report)
+}
+
+/// Text of the content, if it is a text file.
+fn text(content: Option<&Content>) -> Option<&str> {
+    match content {
+        Some(Content::Text(text)) => Some(text),
+        _ => None,
+    }
+}
+
+/// Keep at most `max_lines` lines of the text, noting how many lines are truncated.
+fn truncate_lines(text: &str, max_lines: usize) -> String {
+    let lines = text.lines().collect::<Vec<_>>();
+    if lines.len() <= max_lines {
+        return text.trim_end().to_string();
+    }
+    format!(
+        "{}\n... {} more lines truncated",
+        lines[..max_lines].join("\n"),
+        lines.len() - max_lines
+    )
+}
+
+/// Run the command as child process, handling its exit status per `--on-command-error`.
+/// Returns the exit code to exit the program with, if the command failed or timed out.
+fn run_command(args: &CommandArgs, cwd: Option<&Path>) -> Result<Option<i32>> {
     log::info!("Running command as child process: {:?}", args.command);
-    let mut child = std::process::Command::new(&args.command[0])
-        .args(&args.command[1..])
-        .spawn()?;
+    let mut command = if args.shell {
+        let (shell, flag) = if cfg!(windows) {
+            ("cmd", "/C")
+        } else {
+            ("sh", "-c")
+        };
+        let mut command = std::process::Command::new(shell);
+        command.args([flag, &args.command.join(" ")]);
+        command
+    } else {
+        let mut command = std::process::Command::new(&args.command[0]);
+        command.args(&args.command[1..]);
+        command
+    };
+    command.envs(args.env.iter().map(|(key, value)| (key, value)));
+    if let Some(cwd) = cwd {
+        log::debug!("Running command in: {}", cwd.display());
+        command.current_dir(cwd);
+    }
+    // Only with a timeout, as signals from the terminal, such as Ctrl-C, are sent to the
+    // foreground process group only
+    if args.timeout.is_some() {
+        set_process_group(&mut command);
+    }
+    let mut child = command.spawn()?;
 
-    let status = child.wait()?;
+    let status = match args.timeout {
+        Some(timeout) => match wait_with_timeout(&mut child, timeout)? {
+            Some(status) => status,
+            None => {
+                log::error!("Command timed out after {timeout:?} and was killed.");
+                return Ok(Some(TIMEOUT_EXIT_CODE));
+            }
+        },
+        None => child.wait()?,
+    };
     log::debug!("Command exited with status: {:?}", status);
 
     // Check for exit code
@@ -92,63 +888,82 @@ pub(crate) fn command(args: CommandArgs, _global_opts: GlobalOpts) -> Result<()>
                         "Command exited with non-zero status: {}, propagating exit code.",
                         code
                     );
-                    std::process::exit(code);
+                    return Ok(Some(code));
                 } else {
                     bail!("Command terminated by signal");
                 }
             }
         }
     }
+    Ok(None)
+}
 
-    // Calculate hash again
-    let after_hash = calculate_directory_hash(&target, &args.include, &args.exclude)?;
-    log::info!("Hash after command run: {}", after_hash);
+/// Exit the program with the exit code of the command, which takes precedence over the result of
+/// checking changes. Changes detected are still reported.
+fn exit_with(code: i32, result: Result<()>) -> ! {
+    if let Err(err) = result {
+        log::error!("{err}");
+    }
+    std::process::exit(code);
+}
 
-    // Compare hashes
-    if before_hash != after_hash {
-        bail!(
-            "Hash has changed after running command: {} != {}",
-            before_hash,
-            after_hash
-        );
+/// Wait for the child to exit, killing it if it does not exit within the timeout.
+/// Returns `None` if the child is killed.
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            kill_process_group(child)?;
+            child.wait()?;
+            return Ok(None);
+        }
+        sleep(POLL_INTERVAL.min(deadline - now));
     }
+}
 
-    // No changes detected
-    log::info!("Target hash matches, no changes detected.");
+/// Run the command in its own process group, so that its descendants, such as commands run by
+/// the shell, can be killed along with it.
+#[cfg(unix)]
+fn set_process_group(command: &mut std::process::Command) {
+    use std::os::unix::process::CommandExt;
+
+    command.process_group(0);
+}
+
+/// Process groups are not available on this platform.
+#[cfg(not(unix))]
+fn set_process_group(_command: &mut std::process::Command) {}
+
+/// Kill the child and every process in its process group.
+#[cfg(unix)]
+fn kill_process_group(child: &mut Child) -> Result<()> {
+    let pgid = libc::pid_t::try_from(child.id())?;
+    // SAFETY: `kill` has no memory safety requirements, and the group is led by the child, which
+    // is not waited for yet
+    if unsafe { libc::kill(-pgid, libc::SIGKILL) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
     Ok(())
 }
 
-// NOTE: There is more performant library [merkle_hash](https://github.com/hristogochev/merkle_hash) exists,
-//       but using our version here for more control over hashing process (hasher, include/exclude patterns, etc.)
-// TODO(lasuillard): `DefaultHasher` may change between Rust versions, consider replacing it with more stable hasher
-//                   IF speed becomes an issue, for large file handling (BLAKE3 or xxHash)
-fn calculate_directory_hash(path: &Path, include: &[String], exclude: &[String]) -> Result<String> {
-    log::debug!(
-        "Calculating hash for directory: {}; include: {:?}, exclude: {:?}",
-        path.display(),
-        include,
-        exclude
-    );
-    let mut hasher = DefaultHasher::new();
-    let mut buffer = [0; BUFFER_SIZE];
-    for path in list_files(path, include, exclude) {
-        // ? Should take account directory structure in the hash?
-        if path.is_dir() {
-            log::debug!("Skipping directory: {}", path.display());
-            continue;
-        }
+/// Kill the child only, as process groups are not available on this platform.
+#[cfg(not(unix))]
+fn kill_process_group(child: &mut Child) -> Result<()> {
+    child.kill()?;
+    Ok(())
+}
 
-        log::debug!("Calculating hash for file: {}", path.display());
-        let mut file = File::open(path)?;
-        loop {
-            let bytes_read = file.read(&mut buffer)?;
-            if bytes_read == 0 {
-                break;
-            }
-            buffer[..bytes_read].hash(&mut hasher);
-        }
+/// Parse an environment variable given as `KEY=VALUE`.
+fn parse_env(s: &str) -> Result<(String, String)> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Invalid environment variable `{s}`, expected `KEY=VALUE`"))?;
+    if key.is_empty() {
+        bail!("Invalid environment variable `{s}`, key is empty");
     }
-    let hash = hasher.finish();
-    let hash_as_hex = format!("{:x}", hash);
-    Ok(hash_as_hex)
+    Ok((key.to_string(), value.to_string()))
 }
@@ -1,25 +1,128 @@
-use std::{env::current_dir,
-          path::{PathBuf, absolute}};
+mod report;
 
-use anyhow::{Result, anyhow, bail};
-use clap::Args;
+use std::{collections::{HashMap, HashSet},
+          env::current_dir,
+          fs::{read, read_to_string, remove_file},
+          path::{Path, PathBuf, absolute}};
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::{Args, ValueEnum};
+use glob::Pattern;
 use regex::{self, Regex};
-use strfmt::strfmt;
+use serde::Deserialize;
+use strsim::{levenshtein, normalized_levenshtein};
 use sugars::hmap;
 
+use self::report::{Document, Orphan, OrphanStatus, Record, RuleReport, Status};
 use crate::{GlobalOpts,
-            utils::fs::{list_files, touch_file}};
+            config::{CONFIG_FILE, Config, PYPROJECT_FILE, deserialize_regex},
+            utils::{fs::{create_file, expand_glob, is_glob_pattern, list_files, move_file,
+                         split_extensions},
+                    template::{literal_text, render, render_escaped}}};
+
+/// Maximum number of suggestions to show for a missing pair.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Minimum similarity of filenames for a file to be suggested as a renamed pair.
+const MIN_FILENAME_SIMILARITY: f64 = 0.8;
+
+/// Default pattern for the expected file.
+const DEFAULT_EXPECT: &str = "{to}/{relative_from}/{filename}";
+
+/// Default pattern to include files from the `to` directory.
+const DEFAULT_TO_INCLUDE: &str = "**/*";
+
+/// Name of the rule given as command-line options.
+const DEFAULT_RULE_NAME: &str = "default";
+
+#[derive(ValueEnum, Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum OnRegexMismatch {
+    /// Log a warning and check the file with default variables
+    #[default]
+    Warn,
+
+    /// Skip the file, no pair is required for it
+    Skip,
+
+    /// Exit the program with an error
+    Error,
+}
+
+#[derive(ValueEnum, Clone, Debug, Default, PartialEq, Eq)]
+enum OutputFormat {
+    /// Human-readable logs
+    #[default]
+    Text,
+
+    /// JSON document with a record per source file, printed to standard output
+    Json,
+}
+
+/// Source file whose pair does not exist.
+struct MissingPair {
+    /// Index of the record for the source file.
+    record: usize,
+
+    /// Path to the source file.
+    source: PathBuf,
+
+    /// Path to create the pair at, the first expected pattern which is not a glob.
+    create_path: Option<PathBuf>,
+
+    /// Variables to render the content of the file to create with.
+    vars: HashMap<String, String>,
+}
 
 /// Check for matching file exists.
 #[derive(Args, Debug, Clone)]
 pub(crate) struct CommandArgs {
+    #[command(flatten)]
+    rule: Rule,
+
+    /// Path to the configuration file declaring named rules.
+    ///
+    /// If neither `--from` nor `--to` is given, rules are loaded from this file, defaulting to
+    /// `devobs.toml` or `[tool.devobs]` section of `pyproject.toml` in the current directory.
+    /// Each rule is declared as a `[check-file-pair.<name>]` table, with keys named after the
+    /// command-line options, which cannot be given along with it. Relative paths in rules, such as
+    /// `from` and `to`, are resolved against the current directory, not the directory of the
+    /// configuration file.
+    #[arg(long, conflicts_with_all = ["from", "to"])]
+    config: Option<PathBuf>,
+
+    /// Names of the rules to run from the configuration file. All rules are run if not given.
+    ///
+    /// This option can be specified multiple times or as a comma-separated list.
+    #[arg(long = "rule", num_args = 1.., value_delimiter = ',', conflicts_with_all = ["from", "to"])]
+    rules: Vec<String>,
+
+    /// Output format for the results.
+    ///
+    /// With `json`, logs are written to standard error to keep standard output parseable.
+    #[arg(long, default_value_t, value_enum)]
+    format: OutputFormat,
+}
+
+impl CommandArgs {
+    /// Whether the command writes machine-readable output to standard output.
+    pub(crate) fn is_machine_readable(&self) -> bool {
+        self.format == OutputFormat::Json
+    }
+}
+
+/// Rule to check matching files, given as command-line options or declared in the
+/// configuration file.
+#[derive(Args, Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct Rule {
     /// Directory to check for matching files.
     #[arg(long)]
-    from: String,
+    from: Option<String>,
 
     /// Directory where the expected files should be located.
     #[arg(long)]
-    to: String,
+    to: Option<String>,
 
     /// List of glob patterns to include files from the `from` directory.
     ///
@@ -36,6 +139,12 @@ pub(crate) struct CommandArgs {
     // * Don't forget to update below doc when modifying available variables
     /// Expected pattern for the file in the `to` directory.
     ///
+    /// This option can be specified multiple times; the source file is considered paired if any
+    /// of the expected files exists. A pattern containing glob characters (`*`, `?` or `[`) is
+    /// satisfied by any existing file matching it.
+    ///
+    /// When creating missing files, the first pattern which is not a glob is used.
+    ///
     /// Variables available for substitution:
     ///
     /// - `{cwd}`: current working directory
@@ -48,29 +157,304 @@ pub(crate) struct CommandArgs {
     ///
     /// - `{stem}`: file stem (name without extension)
     ///
-    /// - `{extension}`: file extension
+    /// - `{extension}`: last file extension, empty if the file has no extension
+    ///
+    /// - `{extensions}`: all file extensions, such as `test.ts` for `foo.test.ts`
+    ///
+    /// - `{base}`: file name without any extension, such as `foo` for `foo.test.ts`
+    ///
+    /// - `{parent}`: name of the parent directory of the file
     ///
     /// - `{relative_from}`: relative path from the `from` directory to the file
     ///
-    #[arg(long, default_value = "{to}/{relative_from}/{filename}")]
-    expect: String,
+    /// Variables can be transformed with filters separated by `|`, such as `{stem|snake}`,
+    /// `{stem|kebab}`, `{stem|pascal}`, `{stem|camel}`, `{stem|lower}`, `{stem|upper}`,
+    /// `{stem|replace:_:-}`, `{relative_from|strip_prefix:pkg/}`, `{relative_from|strip_suffix:/v1}`
+    /// or `{name|default:value}` for missing variables. Filters also apply to variables captured by
+    /// `--filename-regex` and `--path-regex`.
+    ///
+    #[arg(long, default_value = DEFAULT_EXPECT)]
+    expect: Vec<String>,
 
     /// Regex to apply to the `filename` variable. Capture groups will then be available
     /// as variables in the `expect` string, overriding the default values.
     #[arg(long)]
+    #[serde(deserialize_with = "deserialize_regex")]
     filename_regex: Option<Regex>,
 
+    /// Regex to apply to the path of the file relative to the `from` directory, such as
+    /// `utils/logger.py`. Named capture groups will then be available as variables in the `expect`
+    /// string, overriding the default values and the captures of `--filename-regex`.
+    #[arg(long)]
+    #[serde(deserialize_with = "deserialize_regex")]
+    path_regex: Option<Regex>,
+
+    /// What to do when `--filename-regex` or `--path-regex` does not match a file.
+    #[arg(long, default_value_t, value_enum)]
+    on_regex_mismatch: OnRegexMismatch,
+
+    /// Require a pair only for source files having at least this many non-blank lines.
+    #[arg(long)]
+    min_lines: Option<usize>,
+
+    /// Require a pair only for source files whose content matches this regex,
+    /// such as `def |pub fn`.
+    #[arg(long)]
+    #[serde(deserialize_with = "deserialize_regex")]
+    require_content: Option<Regex>,
+
+    /// Require a pair only for source files whose content does not match this regex.
+    #[arg(long)]
+    #[serde(deserialize_with = "deserialize_regex")]
+    forbid_content: Option<Regex>,
+
+    /// Require a pair only for source files not larger than this size, in bytes.
+    #[arg(long)]
+    max_size: Option<u64>,
+
     /// If the expected file does not exist, create it.
     #[arg(long)]
     create_if_not_exists: bool,
+
+    /// Path to a template file used as the content of files created by `--create-if-not-exists`.
+    ///
+    /// The template is rendered with the same variables available in `expect`. Literal braces
+    /// must be escaped by doubling them (`{{` and `}}`).
+    #[arg(long, conflicts_with = "create_template_content")]
+    create_template: Option<PathBuf>,
+
+    /// Inline template used as the content of files created by `--create-if-not-exists`.
+    ///
+    /// Same as `--create-template`, but the template is given directly as an argument.
+    #[arg(long)]
+    create_template_content: Option<String>,
+
+    /// Also check for orphaned files in the `to` directory, which no file in the `from`
+    /// directory maps to.
+    #[arg(long)]
+    check_orphans: bool,
+
+    /// List of glob patterns to include files from the `to` directory when checking for orphans
+    /// or looking for renamed or misplaced pairs.
+    ///
+    /// This option can be specified multiple times or as a comma-separated list.
+    #[arg(long, num_args = 1.., value_delimiter = ',', default_value = DEFAULT_TO_INCLUDE)]
+    to_include: Vec<String>,
+
+    /// List of glob patterns to exclude files from the `to` directory when checking for orphans
+    /// or looking for renamed or misplaced pairs.
+    ///
+    /// This option can be specified multiple times or as a comma-separated list.
+    #[arg(long, num_args = 1.., value_delimiter = ',')]
+    to_exclude: Vec<String>,
+
+    /// Delete orphaned files found in the `to` directory.
+    #[arg(long, requires = "check_orphans")]
+    delete_orphans: bool,
+
+    /// Move likely renamed or misplaced files to the expected path of the missing pair.
+    ///
+    /// A file is moved only if it is the single suggestion for the missing pair.
+    #[arg(long)]
+    fix_moves: bool,
+}
+
+impl Rule {
+    /// Command-line options of the rule given with values other than their defaults.
+    fn given_options(&self) -> Vec<&'static str> {
+        let default = Self::default();
+        let regex = |regex: &Option<Regex>| regex.as_ref().map(|r| r.as_str().to_string());
+        [
+            ("--include", self.include != default.include),
+            ("--exclude", self.exclude != default.exclude),
+            ("--expect", self.expect != default.expect),
+            (
+                "--filename-regex",
+                regex(&self.filename_regex) != regex(&default.filename_regex),
+            ),
+            (
+                "--path-regex",
+                regex(&self.path_regex) != regex(&default.path_regex),
+            ),
+            (
+                "--on-regex-mismatch",
+                !matches!(self.on_regex_mismatch, OnRegexMismatch::Warn),
+            ),
+            ("--min-lines", self.min_lines != default.min_lines),
+            (
+                "--require-content",
+                regex(&self.require_content) != regex(&default.require_content),
+            ),
+            (
+                "--forbid-content",
+                regex(&self.forbid_content) != regex(&default.forbid_content),
+            ),
+            ("--max-size", self.max_size != default.max_size),
+            (
+                "--create-if-not-exists",
+                self.create_if_not_exists != default.create_if_not_exists,
+            ),
+            (
+                "--create-template",
+                self.create_template != default.create_template,
+            ),
+            (
+                "--create-template-content",
+                self.create_template_content != default.create_template_content,
+            ),
+            (
+                "--check-orphans",
+                self.check_orphans != default.check_orphans,
+            ),
+            ("--to-include", self.to_include != default.to_include),
+            ("--to-exclude", self.to_exclude != default.to_exclude),
+            (
+                "--delete-orphans",
+                self.delete_orphans != default.delete_orphans,
+            ),
+            ("--fix-moves", self.fix_moves != default.fix_moves),
+        ]
+        .into_iter()
+        .filter_map(|(option, given)| given.then_some(option))
+        .collect()
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self {
+            from: None,
+            to: None,
+            include: vec![],
+            exclude: vec![],
+            expect: vec![DEFAULT_EXPECT.to_string()],
+            filename_regex: None,
+            path_regex: None,
+            on_regex_mismatch: OnRegexMismatch::default(),
+            min_lines: None,
+            require_content: None,
+            forbid_content: None,
+            max_size: None,
+            create_if_not_exists: false,
+            create_template: None,
+            create_template_content: None,
+            check_orphans: false,
+            to_include: vec![DEFAULT_TO_INCLUDE.to_string()],
+            to_exclude: vec![],
+            delete_orphans: false,
+            fix_moves: false,
+        }
+    }
 }
 
 pub(crate) fn command(args: CommandArgs, global_opts: GlobalOpts) -> Result<()> {
-    let mut missing_files = vec![] as Vec<PathBuf>;
+    let from_config = args.rule.from.is_none() && args.rule.to.is_none();
+    let rules = if from_config {
+        let given_options = args.rule.given_options();
+        if !given_options.is_empty() {
+            bail!(
+                "Rule options cannot be given when running rules from the configuration file: {}. \
+                 Specify `--from` and `--to` to run a rule given as command-line options.",
+                given_options.join(", ")
+            );
+        }
+        load_rules(&args)?
+    } else {
+        // Rule given as command-line options
+        vec![(DEFAULT_RULE_NAME.to_string(), args.rule.clone())]
+    };
+
+    let mut reports = vec![] as Vec<RuleReport>;
+    let mut failed_rules = vec![] as Vec<String>;
+    for (name, rule) in &rules {
+        if !from_config {
+            reports.push(run_rule(name, rule, global_opts)?);
+            continue;
+        }
+
+        log::info!("Running rule `{name}`");
+        match run_rule(name, rule, global_opts) {
+            Ok(report) if report.errors.is_empty() => {
+                log::info!("Rule `{name}` passed");
+                reports.push(report);
+            }
+            Ok(report) => {
+                log::error!("Rule `{name}` failed: {}", report.errors.join(" "));
+                failed_rules.push(name.clone());
+                reports.push(report);
+            }
+            Err(err) => {
+                log::error!("Rule `{name}` failed: {err}");
+                failed_rules.push(name.clone());
+            }
+        }
+    }
+
+    // Collect errors before reports are consumed by the document
+    let errors = reports
+        .iter()
+        .flat_map(|report| report.errors.clone())
+        .collect::<Vec<_>>();
+    if args.format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&Document::new(reports))?);
+    }
+
+    if !failed_rules.is_empty() {
+        bail!(
+            "{} of {} rules failed: {}",
+            failed_rules.len(),
+            rules.len(),
+            failed_rules.join(", ")
+        );
+    }
+    if !errors.is_empty() {
+        bail!(errors.join(" "));
+    }
+
+    Ok(())
+}
+
+/// Load rules to run from the configuration file.
+fn load_rules(args: &CommandArgs) -> Result<Vec<(String, Rule)>> {
+    let config = match &args.config {
+        Some(path) => Config::load(path)?,
+        None => Config::discover(&current_dir()?)?.ok_or(anyhow!(
+            "No rules to run. Specify `--from` and `--to`, or declare rules in `{CONFIG_FILE}` \
+             or `[tool.devobs]` section of `{PYPROJECT_FILE}`."
+        ))?,
+    };
+    let mut rules = config.check_file_pair;
+    if !args.rules.is_empty() {
+        if let Some(unknown) = args.rules.iter().find(|name| !rules.contains_key(*name)) {
+            bail!(
+                "Unknown rule `{unknown}`, available rules: {}",
+                rules.keys().cloned().collect::<Vec<_>>().join(", ")
+            );
+        }
+        rules.retain(|name, _| args.rules.contains(name));
+    }
+    if rules.is_empty() {
+        bail!("No rules to run, no `check-file-pair` rules declared in the configuration file.");
+    }
+
+    Ok(rules.into_iter().collect())
+}
+
+/// Check matching files for a single rule.
+fn run_rule(name: &str, rule: &Rule, global_opts: GlobalOpts) -> Result<RuleReport> {
+    let mut records = vec![] as Vec<Record>;
+    let mut missing_files = vec![] as Vec<MissingPair>;
+    let mut expected_files = HashSet::new() as HashSet<PathBuf>;
 
     // Preprocess options
-    let from = absolute(PathBuf::from(&args.from))?;
-    let to = absolute(PathBuf::from(&args.to))?;
+    let (Some(from), Some(to)) = (&rule.from, &rule.to) else {
+        bail!("Rule `{name}` requires both `from` and `to` directories.");
+    };
+    if rule.delete_orphans && !rule.check_orphans {
+        bail!("Deleting orphaned files requires `check-orphans` to be enabled.");
+    }
+    let from = absolute(PathBuf::from(from))?;
+    let to = absolute(PathBuf::from(to))?;
     let cwd = current_dir()?;
 
     // Prepare base variables for substitution
@@ -81,7 +465,17 @@ pub(crate) fn command(args: CommandArgs, global_opts: GlobalOpts) -> Result<()>
     };
     log::debug!("Prepared base variables: {base_vars:?}");
 
-    for path in list_files(&from, &args.include, &args.exclude) {
+    // Load template for the content of created files, only if files are to be created
+    let create_template = match (&rule.create_template, &rule.create_template_content) {
+        _ if !rule.create_if_not_exists => String::new(),
+        (Some(path), _) => read_to_string(path)
+            .with_context(|| format!("Failed to read template file: {}", path.display()))?,
+        (None, Some(content)) => content.clone(),
+        (None, None) => String::new(),
+    };
+
+    let source_files = list_files(&from, &rule.include, &rule.exclude);
+    for path in source_files.clone() {
         log::trace!("Checking file {}", path.display());
 
         let filename = path.file_name().expect("Failed to get file name");
@@ -93,9 +487,22 @@ pub(crate) fn command(args: CommandArgs, global_opts: GlobalOpts) -> Result<()>
 
         let extension = path
             .extension()
-            .ok_or(anyhow!("Failed to get file extension"))?
-            .to_str()
-            .ok_or(anyhow!("Failed to convert file extension to string"))?;
+            .map(|ext| {
+                ext.to_str()
+                    .ok_or(anyhow!("Failed to convert file extension to string"))
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let (base, extensions) = split_extensions(
+            filename
+                .to_str()
+                .ok_or(anyhow!("Failed to convert file name to string"))?,
+        );
+        let parent = path
+            .parent()
+            .and_then(|p| p.file_name())
+            .map(|p| p.to_str().unwrap_or_default())
+            .unwrap_or_default();
 
         let relative_from = path
             .strip_prefix(&from)?
@@ -108,67 +515,385 @@ pub(crate) fn command(args: CommandArgs, global_opts: GlobalOpts) -> Result<()>
         let mut vars = base_vars.clone();
         vars.insert("stem".to_string(), stem);
         vars.insert("extension".to_string(), extension);
+        vars.insert("base".to_string(), base);
+        vars.insert("extensions".to_string(), extensions);
+        vars.insert("parent".to_string(), parent);
         vars.insert("relative_from".to_string(), &relative_from);
         vars.insert("filename".to_string(), filename.to_str().unwrap());
 
-        // Populate from user-provided filename regex
-        if let Some(ref regex) = args.filename_regex {
-            if let Some(captures) = regex.captures(filename.to_str().unwrap()) {
-                for (name, value) in regex
-                    .capture_names()
-                    .flatten()
-                    .filter_map(|n| Some((n, captures.name(n)?.as_str())))
-                {
-                    vars.insert(name.to_string(), value);
+        // Populate from user-provided regexes
+        let relative_path = path
+            .strip_prefix(&from)?
+            .to_str()
+            .ok_or(anyhow!("Failed to convert relative path to string"))?;
+        let mut skip = None as Option<String>;
+        for (kind, regex, value) in [
+            ("Filename", &rule.filename_regex, filename.to_str().unwrap()),
+            ("Path", &rule.path_regex, relative_path),
+        ] {
+            let Some(regex) = regex else {
+                continue;
+            };
+            if capture_vars(regex, value, &mut vars) {
+                continue;
+            }
+            match rule.on_regex_mismatch {
+                OnRegexMismatch::Warn => {
+                    log::warn!(
+                        "{kind} regex did not match for file {}: {value}",
+                        path.display()
+                    );
+                }
+                OnRegexMismatch::Skip => {
+                    log::debug!(
+                        "{kind} regex did not match for file {}, skipping: {value}",
+                        path.display()
+                    );
+                    skip = Some(format!("{} regex did not match", kind.to_lowercase()));
+                }
+                OnRegexMismatch::Error => {
+                    bail!(
+                        "{kind} regex did not match for file {}: {value}",
+                        path.display()
+                    );
                 }
-            } else {
-                log::warn!(
-                    "Filename regex did not match for file {}: {}",
-                    path.display(),
-                    filename.to_str().unwrap()
-                );
             }
         }
+        let variables = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        if let Some(reason) = skip {
+            records.push(Record {
+                rule: name.to_string(),
+                source: path,
+                expected: vec![],
+                variables,
+                status: Status::Skipped,
+                reason: Some(reason),
+            });
+            continue;
+        }
         log::debug!("Prepared substitution variables for file {path:?}: {vars:?}");
 
-        // Render the expected file path
-        let result = strfmt(&args.expect, &vars)?;
-        log::trace!("Formatted result: {result}");
+        // Render the expected file paths
+        let mut candidates = vec![] as Vec<PathBuf>;
+        let mut create_path = None as Option<PathBuf>;
+        let mut exists = false;
+        for expect in &rule.expect {
+            // Whether the expectation is a glob depends on the template only, as substituted
+            // values may contain glob characters, such as `pages/[id].tsx`, kept literal by escaping
+            let is_glob = is_glob_pattern(&literal_text(expect)?);
+            let result = if is_glob {
+                render_escaped(expect, &vars, Pattern::escape)?
+            } else {
+                render(expect, &vars)?
+            };
+            log::trace!("Formatted result: {result}");
+
+            let result_path = absolute(PathBuf::from(&result))?;
+            log::trace!("Resolved result path: {}", result_path.display());
+            if is_glob {
+                let matches = expand_glob(&to, &[result_path.to_string_lossy().to_string()]);
+                log::trace!("Glob {} matched: {matches:?}", result_path.display());
+                exists |= !matches.is_empty();
+                expected_files.extend(matches);
+            } else {
+                exists |= result_path.exists();
+                expected_files.insert(result_path.clone());
+                create_path.get_or_insert_with(|| result_path.clone());
+            }
+            candidates.push(result_path);
+        }
 
-        let result_path = absolute(PathBuf::from(&result))?;
-        log::trace!("Resolved result path: {}", result_path.display());
+        let mut record = Record {
+            rule: name.to_string(),
+            source: path.clone(),
+            expected: candidates.clone(),
+            variables,
+            status: Status::Present,
+            reason: None,
+        };
 
-        // Check if the expected file exists
-        if result_path.exists() {
-            log::debug!("Expected file exists: {}", result_path.display());
+        // Check whether the source file requires a pair at all
+        if let Some(reason) = skip_reason(&path, rule)? {
+            log::debug!("Skipping file {}: {reason}", path.display());
+            record.status = Status::Skipped;
+            record.reason = Some(reason);
+            records.push(record);
+            continue;
+        }
+
+        // Check if any of the expected files exists
+        if exists {
+            log::debug!("Expected file exists for {}", path.display());
+            records.push(record);
             continue;
         }
 
         log::warn!(
             "Pair of file {} does not exist: {}",
             path.display(),
-            result_path.display(),
+            candidates
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
         );
-        missing_files.push(result_path);
+        let vars = vars
+            .into_iter()
+            .map(|(key, value)| (key, value.to_string()))
+            .collect();
+        record.status = Status::Missing;
+        records.push(record);
+        missing_files.push(MissingPair {
+            record: records.len() - 1,
+            source: path,
+            create_path,
+            vars,
+        });
+    }
+
+    // Look for files in the `to` directory that no source file maps to. Source files are left
+    // out, as `from` and `to` may overlap when pairs sit next to their sources.
+    let source_files = source_files.into_iter().collect::<HashSet<_>>();
+    let mut unpaired_files = list_files(&to, &rule.to_include, &rule.to_exclude)
+        .into_iter()
+        .filter(|path| {
+            path.is_file() && !expected_files.contains(path) && !source_files.contains(path)
+        })
+        .collect::<Vec<_>>();
+
+    // Suggest unpaired files which are likely renamed or misplaced pairs, and move them if requested
+    let mut moved_files = 0;
+    missing_files.retain(|missing| {
+        let Some(create_path) = &missing.create_path else {
+            return true;
+        };
+        let suggestions = suggest_pairs(create_path, &unpaired_files, &to);
+        for suggestion in suggestions.iter().take(MAX_SUGGESTIONS) {
+            log::warn!(
+                "Did you mean {} for {}?",
+                suggestion.display(),
+                create_path.display()
+            );
+        }
+        if !rule.fix_moves || suggestions.is_empty() {
+            return true;
+        }
+        if suggestions.len() > 1 {
+            log::warn!(
+                "Not moving any file to {}, there are multiple suggestions",
+                create_path.display()
+            );
+            return true;
+        }
+
+        let suggestion = suggestions[0].clone();
+        log::warn!(
+            "Moving misplaced file {} to {}",
+            suggestion.display(),
+            create_path.display()
+        );
+        if !global_opts.dry_run
+            && let Err(err) = move_file(&suggestion, create_path)
+        {
+            log::error!("Failed to move file {}: {err}", suggestion.display());
+            return true;
+        }
+        unpaired_files.retain(|p| p != &suggestion);
+        records[missing.record].status = Status::Moved;
+        moved_files += 1;
+        false
+    });
+
+    let mut orphan_files = vec![] as Vec<Orphan>;
+    if rule.check_orphans {
+        for path in unpaired_files {
+            log::warn!(
+                "Orphaned file {} has no source in {}",
+                path.display(),
+                from.display(),
+            );
+            orphan_files.push(Orphan {
+                rule: name.to_string(),
+                path,
+                status: OrphanStatus::Orphaned,
+            });
+        }
     }
 
     // Check missing files and create if requested
+    let mut errors = vec![] as Vec<String>;
+    if moved_files > 0 {
+        errors.push(format!("Moved {moved_files} misplaced files."));
+    }
     if !missing_files.is_empty() {
-        if !args.create_if_not_exists {
-            bail!(
+        if rule.create_if_not_exists {
+            let mut not_created = 0;
+            for missing in &missing_files {
+                let Some(create_path) = &missing.create_path else {
+                    log::warn!(
+                        "Cannot create pair of file {}: all expected patterns are globs",
+                        missing.source.display()
+                    );
+                    not_created += 1;
+                    continue;
+                };
+                // Rendered only when creating, as variables used by the template may be missing
+                // for files which are only checked
+                let content = render(&create_template, &missing.vars)?;
+                log::warn!("Creating missing file: {}", create_path.display());
+                if !global_opts.dry_run {
+                    create_file(create_path, &content)?;
+                }
+                records[missing.record].status = Status::Created;
+            }
+            if not_created < missing_files.len() {
+                errors.push(format!(
+                    "Created {} missing files.",
+                    missing_files.len() - not_created
+                ));
+            }
+            if not_created > 0 {
+                errors.push(format!(
+                    "There are {not_created} missing files which cannot be created."
+                ));
+            }
+        } else {
+            errors.push(format!(
                 "There are {} missing files. Use `--create-if-not-exists` to create them.",
                 missing_files.len()
-            );
+            ));
         }
-        for missing in &missing_files {
-            log::warn!("Creating missing file: {}", missing.display());
-            if !global_opts.dry_run {
-                touch_file(missing)?;
+    }
+
+    // Check orphaned files and delete if requested
+    if !orphan_files.is_empty() {
+        if rule.delete_orphans {
+            for orphan in &mut orphan_files {
+                log::warn!("Deleting orphaned file: {}", orphan.path.display());
+                if !global_opts.dry_run {
+                    remove_file(&orphan.path)?;
+                }
+                orphan.status = OrphanStatus::Deleted;
             }
+            errors.push(format!("Deleted {} orphaned files.", orphan_files.len()));
+        } else {
+            errors.push(format!(
+                "There are {} orphaned files. Use `--delete-orphans` to delete them.",
+                orphan_files.len()
+            ));
         }
-        bail!("Created {} missing files.", missing_files.len());
     }
-    log::info!("Everything is fine, no missing files.");
 
-    Ok(())
+    if errors.is_empty() {
+        log::info!("Everything is fine, no missing files.");
+    }
+
+    Ok(RuleReport {
+        records,
+        orphans: orphan_files,
+        errors,
+    })
+}
+
+/// Apply the regex to the value, inserting named capture groups into `vars`.
+///
+/// Returns `false` if the regex did not match.
+fn capture_vars<'a>(regex: &Regex, value: &'a str, vars: &mut HashMap<String, &'a str>) -> bool {
+    let Some(captures) = regex.captures(value) else {
+        return false;
+    };
+    for (name, value) in regex
+        .capture_names()
+        .flatten()
+        .filter_map(|n| Some((n, captures.name(n)?.as_str())))
+    {
+        vars.insert(name.to_string(), value);
+    }
+    true
+}
+
+/// Check the source file against the content predicates, returning the reason
+/// if the file does not require a pair.
+fn skip_reason(path: &Path, rule: &Rule) -> Result<Option<String>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    if let Some(max_size) = rule.max_size {
+        let size = path.metadata()?.len();
+        if size > max_size {
+            return Ok(Some(format!(
+                "file size {size} bytes exceeds maximum of {max_size} bytes"
+            )));
+        }
+    }
+
+    if rule.min_lines.is_none() && rule.require_content.is_none() && rule.forbid_content.is_none() {
+        return Ok(None);
+    }
+    let content = String::from_utf8_lossy(&read(path)?).to_string();
+
+    if let Some(min_lines) = rule.min_lines {
+        let lines = content.lines().filter(|l| !l.trim().is_empty()).count();
+        if lines < min_lines {
+            return Ok(Some(format!(
+                "{lines} non-blank lines is less than minimum of {min_lines}"
+            )));
+        }
+    }
+
+    if let Some(regex) = &rule.require_content
+        && !regex.is_match(&content)
+    {
+        return Ok(Some(format!(
+            "content does not match required regex `{regex}`"
+        )));
+    }
+
+    if let Some(regex) = &rule.forbid_content
+        && regex.is_match(&content)
+    {
+        return Ok(Some(format!("content matches forbidden regex `{regex}`")));
+    }
+
+    Ok(None)
+}
+
+/// Find files which are likely renamed or misplaced pairs of the expected file, ordered by
+/// similarity; files with the same or a similar filename, closest directory first.
+fn suggest_pairs(expected: &Path, candidates: &[PathBuf], to: &Path) -> Vec<PathBuf> {
+    let filename = |path: &Path| {
+        path.file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    let parent = |path: &Path| {
+        path.strip_prefix(to)
+            .unwrap_or(path)
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    let expected_filename = filename(expected);
+    let expected_parent = parent(expected);
+
+    let mut suggestions = candidates
+        .iter()
+        .filter_map(|candidate| {
+            let candidate_filename = filename(candidate);
+            if normalized_levenshtein(&expected_filename, &candidate_filename)
+                < MIN_FILENAME_SIMILARITY
+            {
+                return None;
+            }
+            let filename_distance = levenshtein(&expected_filename, &candidate_filename);
+            let parent_distance = levenshtein(&expected_parent, &parent(candidate));
+            Some(((filename_distance, parent_distance), candidate))
+        })
+        .collect::<Vec<_>>();
+    suggestions.sort();
+    suggestions.into_iter().map(|(_, c)| c.clone()).collect()
 }
@@ -0,0 +1,143 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use serde::Serialize;
+
+/// Version of the JSON document format, bumped on incompatible changes.
+const FORMAT_VERSION: u32 = 1;
+
+/// Status of a source file after checking its pair.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(super) enum Status {
+    /// Any of the expected files exists.
+    Present,
+
+    /// None of the expected files exists.
+    Missing,
+
+    /// The expected file has been created.
+    Created,
+
+    /// A misplaced file has been moved to the expected path.
+    Moved,
+
+    /// The source file does not require a pair.
+    Skipped,
+}
+
+/// Result of checking a single source file.
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct Record {
+    /// Name of the rule which checked the file.
+    pub(super) rule: String,
+
+    /// Path to the source file.
+    pub(super) source: PathBuf,
+
+    /// Rendered paths of the expected files, in the order of the `expect` patterns.
+    pub(super) expected: Vec<PathBuf>,
+
+    /// Variables used to render the expected paths.
+    pub(super) variables: BTreeMap<String, String>,
+
+    /// Status of the source file.
+    pub(super) status: Status,
+
+    /// Reason why the source file has been skipped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) reason: Option<String>,
+}
+
+/// Status of a file in the `to` directory which no source file maps to.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(super) enum OrphanStatus {
+    /// The orphaned file still exists.
+    Orphaned,
+
+    /// The orphaned file has been deleted.
+    Deleted,
+}
+
+/// File in the `to` directory which no source file maps to.
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct Orphan {
+    /// Name of the rule which found the file.
+    pub(super) rule: String,
+
+    /// Path to the orphaned file.
+    pub(super) path: PathBuf,
+
+    /// Status of the orphaned file.
+    pub(super) status: OrphanStatus,
+}
+
+/// Results of running a single rule.
+#[derive(Debug, Default)]
+pub(super) struct RuleReport {
+    /// Results for each source file.
+    pub(super) records: Vec<Record>,
+
+    /// Orphaned files found in the `to` directory.
+    pub(super) orphans: Vec<Orphan>,
+
+    /// Problems found by the rule, causing the command to fail.
+    pub(super) errors: Vec<String>,
+}
+
+/// Number of files for each status.
+#[derive(Serialize, Debug, Default)]
+struct Totals {
+    present: usize,
+    missing: usize,
+    created: usize,
+    moved: usize,
+    skipped: usize,
+    orphaned: usize,
+    deleted: usize,
+}
+
+/// Machine-readable document of the command results.
+#[derive(Serialize, Debug)]
+pub(super) struct Document {
+    version: u32,
+    records: Vec<Record>,
+    orphans: Vec<Orphan>,
+    totals: Totals,
+}
+
+impl Document {
+    /// Build the document from the reports of all rules run.
+    pub(super) fn new(reports: Vec<RuleReport>) -> Self {
+        let mut records = vec![] as Vec<Record>;
+        let mut orphans = vec![] as Vec<Orphan>;
+        for report in reports {
+            records.extend(report.records);
+            orphans.extend(report.orphans);
+        }
+
+        let mut totals = Totals::default();
+        for record in &records {
+            match record.status {
+                Status::Present => totals.present += 1,
+                Status::Missing => totals.missing += 1,
+                Status::Created => totals.created += 1,
+                Status::Moved => totals.moved += 1,
+                Status::Skipped => totals.skipped += 1,
+            }
+        }
+        for orphan in &orphans {
+            match orphan.status {
+                OrphanStatus::Orphaned => totals.orphaned += 1,
+                OrphanStatus::Deleted => totals.deleted += 1,
+            }
+        }
+
+        Self {
+            version: FORMAT_VERSION,
+            records,
+            orphans,
+            totals,
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::{fs::{read_to_string, write},
+          num::NonZeroUsize,
+          path::{PathBuf, absolute}};
+
+use anyhow::{Context, Result, bail};
+use clap::{Args, Subcommand};
+use serde::{Deserialize, Serialize};
+
+use crate::{GlobalOpts,
+            utils::{hash::HashAlgorithm,
+                    manifest::{Changes, DirectoryHash, HashOptions, Track,
+                               calculate_directory_hash},
+                    normalize::Normalization}};
+
+/// Version of the manifest file format, bumped on incompatible changes.
+const MANIFEST_VERSION: u32 = 2;
+
+/// Saves file hashes of the target directory to a manifest, to verify the directory against it
+/// later. Unlike `assert-diff`, the command changing the directory may run in a separate step.
+#[derive(Args, Debug, Clone)]
+pub(crate) struct CommandArgs {
+    #[command(subcommand)]
+    action: Action,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Action {
+    /// Save hashes of files in the target directory to a manifest file.
+    Save(SaveArgs),
+
+    /// Verify the target directory against a manifest file. Raises an error if any changes are
+    /// detected.
+    Verify(VerifyArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+struct SaveArgs {
+    /// Target directory to save the snapshot of.
+    #[arg(long)]
+    target: String,
+
+    /// List of glob patterns to include files from the `target` directory.
+    ///
+    /// This option can be specified multiple times or as a comma-separated list.
+    #[arg(long, num_args = 1.., value_delimiter = ',', default_value = "**/*")]
+    include: Vec<String>,
+
+    /// List of glob patterns to exclude files from the `target` directory.
+    ///
+    /// This option can be specified multiple times or as a comma-separated list.
+    #[arg(long, num_args = 1.., value_delimiter = ',')]
+    exclude: Vec<String>,
+
+    /// Additional attributes to track for changes, besides file paths and contents.
+    ///
+    /// This option can be specified multiple times or as a comma-separated list.
+    #[arg(long, num_args = 1.., value_delimiter = ',', value_enum)]
+    track: Vec<Track>,
+
+    /// Algorithm used to hash file contents. Digests are stable across versions and platforms.
+    #[arg(long, default_value_t, value_enum)]
+    hash_algorithm: HashAlgorithm,
+
+    /// Number of threads to hash files with. Defaults to the number of available CPUs.
+    /// The result is the same regardless of the number of threads.
+    #[arg(long)]
+    jobs: Option<NonZeroUsize>,
+
+    /// Path to write the manifest file to.
+    #[arg(long)]
+    out: PathBuf,
+}
+
+#[derive(Args, Debug, Clone)]
+struct VerifyArgs {
+    /// Path to the manifest file written by `snapshot save`.
+    manifest: PathBuf,
+
+    /// Target directory to verify. Defaults to the target recorded in the manifest.
+    #[arg(long)]
+    target: Option<String>,
+
+    /// Number of threads to hash files with. Defaults to the number of available CPUs.
+    /// The result is the same regardless of the number of threads.
+    #[arg(long)]
+    jobs: Option<NonZeroUsize>,
+}
+
+/// Manifest file, recording options used to walk the target directory along with the hashes,
+/// so the directory is walked the same way on verification.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+struct Manifest {
+    version: u32,
+    target: String,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    track: Vec<Track>,
+    hash_algorithm: HashAlgorithm,
+    #[serde(flatten)]
+    directory: DirectoryHash,
+}
+
+pub(crate) fn command(args: CommandArgs, global_opts: GlobalOpts) -> Result<()> {
+    match args.action {
+        Action::Save(args) => save(args, global_opts),
+        Action::Verify(args) => verify(args),
+    }
+}
+
+fn save(args: SaveArgs, global_opts: GlobalOpts) -> Result<()> {
+    let target = absolute(PathBuf::from(&args.target))?;
+    if !target.exists() {
+        bail!("Target path does not exist: {}", target.display());
+    }
+
+    let directory = calculate_directory_hash(
+        &target,
+        &HashOptions {
+            include: &args.include,
+            exclude: &args.exclude,
+            track: &args.track,
+            algorithm: args.hash_algorithm,
+            jobs: args.jobs,
+            normalization: &Normalization::default(),
+        },
+    )?;
+    log::info!(
+        "Hash of {} files in target: {}",
+        directory.files.len(),
+        directory.hash
+    );
+
+    let manifest = Manifest {
+        version: MANIFEST_VERSION,
+        target: args.target,
+        include: args.include,
+        exclude: args.exclude,
+        track: args.track,
+        hash_algorithm: args.hash_algorithm,
+        directory,
+    };
+    log::info!("Saving snapshot to manifest: {}", args.out.display());
+    if !global_opts.dry_run {
+        write(&args.out, serde_json::to_string_pretty(&manifest)? + "\n")
+            .with_context(|| format!("Failed to write manifest file: {}", args.out.display()))?;
+    }
+    Ok(())
+}
+
+fn verify(args: VerifyArgs) -> Result<()> {
+    let content = read_to_string(&args.manifest)
+        .with_context(|| format!("Failed to read manifest file: {}", args.manifest.display()))?;
+    let manifest = serde_json::from_str::<Manifest>(&content)
+        .with_context(|| format!("Failed to parse manifest file: {}", args.manifest.display()))?;
+    if manifest.version != MANIFEST_VERSION {
+        bail!(
+            "Unsupported manifest version: {} (expected {})",
+            manifest.version,
+            MANIFEST_VERSION
+        );
+    }
+
+    let target = absolute(PathBuf::from(args.target.unwrap_or(manifest.target)))?;
+    if !target.exists() {
+        bail!("Target path does not exist: {}", target.display());
+    }
+    log::info!("Hash in manifest: {}", manifest.directory.hash);
+
+    let current = calculate_directory_hash(
+        &target,
+        &HashOptions {
+            include: &manifest.include,
+            exclude: &manifest.exclude,
+            track: &manifest.track,
+            algorithm: manifest.hash_algorithm,
+            jobs: args.jobs,
+            normalization: &Normalization::default(),
+        },
+    )?;
+    log::info!("Hash of target: {}", current.hash);
+
+    let changes = Changes::between(&manifest.directory, &current);
+    if manifest.directory.hash != current.hash || !changes.is_empty() {
+        bail!(
+            "Target has changed since the snapshot: {} != {}{}",
+            manifest.directory.hash,
+            current.hash,
+            changes
+        );
+    }
+
+    log::info!("Target hash matches the snapshot, no changes detected.");
+    Ok(())
+}
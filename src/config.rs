@@ -0,0 +1,191 @@
+use std::{collections::BTreeMap, fs::read_to_string, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use regex::Regex;
+use serde::{Deserialize, Deserializer, de::Error};
+
+use crate::commands::check_file_pair;
+
+/// Name of the configuration file, looked up in the current directory.
+pub(crate) const CONFIG_FILE: &str = "devobs.toml";
+
+/// Name of the Python project file, whose `[tool.devobs]` section is used as configuration.
+pub(crate) const PYPROJECT_FILE: &str = "pyproject.toml";
+
+/// Project configuration, declared in `devobs.toml` or `[tool.devobs]` section of `pyproject.toml`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct Config {
+    /// Named rules for the `check-file-pair` command.
+    pub(crate) check_file_pair: BTreeMap<String, check_file_pair::Rule>,
+}
+
+/// Part of `pyproject.toml` relevant to the configuration, other sections are ignored.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct PyProject {
+    tool: PyProjectTool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct PyProjectTool {
+    devobs: Option<Config>,
+}
+
+impl Config {
+    /// Load the configuration from the given file. If the file is `pyproject.toml`,
+    /// the configuration is read from its `[tool.devobs]` section.
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        log::debug!("Loading configuration from {}", path.display());
+        let content = read_to_string(path)
+            .with_context(|| format!("Failed to read configuration file: {}", path.display()))?;
+        let context = || format!("Failed to parse configuration file: {}", path.display());
+        if path.file_name().is_some_and(|name| name == PYPROJECT_FILE) {
+            let pyproject = toml::from_str::<PyProject>(&content).with_context(context)?;
+            return pyproject.tool.devobs.ok_or(anyhow!(
+                "No `[tool.devobs]` section in configuration file: {}",
+                path.display()
+            ));
+        }
+        toml::from_str(&content).with_context(context)
+    }
+
+    /// Look up the configuration in the given directory, preferring `devobs.toml` over
+    /// `[tool.devobs]` section of `pyproject.toml`.
+    pub(crate) fn discover(dir: &Path) -> Result<Option<Self>> {
+        let config_file = dir.join(CONFIG_FILE);
+        if config_file.is_file() {
+            return Self::load(&config_file).map(Some);
+        }
+
+        let pyproject_file = dir.join(PYPROJECT_FILE);
+        if pyproject_file.is_file() {
+            let content = read_to_string(&pyproject_file)?;
+            let pyproject = toml::from_str::<PyProject>(&content).with_context(|| {
+                format!(
+                    "Failed to parse configuration file: {}",
+                    pyproject_file.display()
+                )
+            })?;
+            if let Some(config) = pyproject.tool.devobs {
+                log::debug!("Loaded configuration from {}", pyproject_file.display());
+                return Ok(Some(config));
+            }
+        }
+
+        log::debug!("No configuration file found in {}", dir.display());
+        Ok(None)
+    }
+}
+
+/// Deserialize an optional regex from a string.
+pub(crate) fn deserialize_regex<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| Regex::new(&s).map_err(D::Error::custom))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use sugars::hmap;
+
+    use super::*;
+    use crate::helpers::get_temp_dir;
+
+    #[test]
+    fn test_load_config_file() -> Result<()> {
+        // Arrange
+        let temp_dir = get_temp_dir(hmap! {
+            "devobs.toml" => r#"
+[check-file-pair.python]
+from = "src"
+to = "tests"
+include = ["**/*.py"]
+filename-regex = "^(?P<name>.*)$"
+
+[check-file-pair.rust]
+from = "crates"
+to = "tests"
+"#,
+        });
+
+        // Act
+        let config = Config::load(&temp_dir.path().join("devobs.toml"))?;
+
+        // Assert
+        assert_eq!(
+            config.check_file_pair.keys().collect::<Vec<_>>(),
+            &["python", "rust"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_invalid_regex() {
+        // Arrange
+        let temp_dir = get_temp_dir(hmap! {
+            "devobs.toml" => r#"
+[check-file-pair.python]
+filename-regex = "^(?P<name>.*$"
+"#,
+        });
+
+        // Act
+        let result = Config::load(&temp_dir.path().join("devobs.toml"));
+
+        // Assert
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_discover_pyproject() -> Result<()> {
+        // Arrange
+        let temp_dir = get_temp_dir(hmap! {
+            "pyproject.toml" => r#"
+[project]
+name = "example"
+
+[tool.devobs.check-file-pair.python]
+from = "src"
+to = "tests"
+"#,
+        });
+
+        // Act
+        let config = Config::discover(temp_dir.path())?;
+
+        // Assert
+        assert_eq!(
+            config
+                .expect("Configuration should be found")
+                .check_file_pair
+                .keys()
+                .collect::<Vec<_>>(),
+            &["python"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_discover_pyproject_without_section() -> Result<()> {
+        // Arrange
+        let temp_dir = get_temp_dir(hmap! {
+            "pyproject.toml" => r#"
+[project]
+name = "example"
+"#,
+        });
+
+        // Act
+        let config = Config::discover(temp_dir.path())?;
+
+        // Assert
+        assert!(config.is_none());
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 mod commands;
+mod config;
 #[cfg(test)]
 #[path = "../tests/helpers.rs"]
 pub(crate) mod helpers;
@@ -45,8 +46,19 @@ struct GlobalOpts {
 
 #[derive(Subcommand, Debug, Clone)]
 enum Commands {
-    CheckFilePair(crate::commands::check_file_pair::CommandArgs),
-    AssertDiff(crate::commands::assert_diff::CommandArgs),
+    CheckFilePair(Box<crate::commands::check_file_pair::CommandArgs>),
+    AssertDiff(Box<crate::commands::assert_diff::CommandArgs>),
+    Snapshot(crate::commands::snapshot::CommandArgs),
+}
+
+impl Commands {
+    /// Whether the command writes machine-readable output to standard output.
+    fn is_machine_readable(&self) -> bool {
+        match self {
+            Commands::CheckFilePair(args) => args.is_machine_readable(),
+            Commands::AssertDiff(_) | Commands::Snapshot(_) => false,
+        }
+    }
 }
 
 // TODO(lasuillard): Customize log formatter
@@ -82,11 +94,18 @@ async fn _main(args: Cli) -> Result<()> {
         ColorChoice::Auto
     };
 
+    // Keep standard output clean for machine-readable output
+    let terminal_mode = if args.command.is_machine_readable() {
+        TerminalMode::Stderr
+    } else {
+        TerminalMode::Mixed
+    };
+
     // Initialize the logger
     TermLogger::init(
         log_level,
         config_builder.build(),
-        TerminalMode::Mixed,
+        terminal_mode,
         color_choice,
     )?;
 
@@ -96,9 +115,10 @@ async fn _main(args: Cli) -> Result<()> {
     log::debug!("Running command {:?} at {:?}", args.command, current_dir());
     match args.command {
         Commands::CheckFilePair(args) => {
-            crate::commands::check_file_pair::command(args, global_opts)
+            crate::commands::check_file_pair::command(*args, global_opts)
         }
-        Commands::AssertDiff(args) => crate::commands::assert_diff::command(args, global_opts),
+        Commands::AssertDiff(args) => crate::commands::assert_diff::command(*args, global_opts),
+        Commands::Snapshot(args) => crate::commands::snapshot::command(args, global_opts),
     }
 }
 
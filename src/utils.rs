@@ -1 +1,10 @@
+pub(crate) mod diff;
+pub(crate) mod duration;
 pub(crate) mod fs;
+pub(crate) mod git;
+pub(crate) mod hash;
+pub(crate) mod manifest;
+pub(crate) mod normalize;
+pub(crate) mod semantic;
+pub(crate) mod target;
+pub(crate) mod template;
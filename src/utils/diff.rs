@@ -0,0 +1,130 @@
+use std::{fs::{metadata, read},
+          path::Path};
+
+use anyhow::Result;
+use similar::TextDiff;
+
+/// Number of context lines around changes in unified diffs, same as `git diff`.
+const CONTEXT_LINES: usize = 3;
+
+/// Content of a file, kept to show what has changed in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Content {
+    Text(String),
+    Binary,
+    TooLarge,
+}
+
+impl Content {
+    /// Read the file, unless it is larger than `max_size` bytes. Files containing NUL bytes or
+    /// invalid UTF-8 are considered binary.
+    pub(crate) fn read(path: &Path, max_size: u64) -> Result<Self> {
+        if metadata(path)?.len() > max_size {
+            return Ok(Self::TooLarge);
+        }
+        let bytes = read(path)?;
+        if bytes.contains(&0) {
+            return Ok(Self::Binary);
+        }
+        Ok(String::from_utf8(bytes).map_or(Self::Binary, Self::Text))
+    }
+}
+
+/// Unified diff of a text file in git format, with `a/` and `b/` path prefixes, so it can be
+/// applied with `git apply` or `patch -p1`. Missing `before` or `after` means the file is
+/// created or deleted.
+pub(crate) fn unified_diff(path: &str, before: Option<&str>, after: Option<&str>) -> String {
+    let mut result = format!("diff --git a/{path} b/{path}\n");
+    if before.is_none() {
+        result.push_str("new file mode 100644\n");
+    }
+    if after.is_none() {
+        result.push_str("deleted file mode 100644\n");
+    }
+    let old_header = before.map_or("/dev/null".to_string(), |_| format!("a/{path}"));
+    let new_header = after.map_or("/dev/null".to_string(), |_| format!("b/{path}"));
+    let diff = TextDiff::from_lines(before.unwrap_or_default(), after.unwrap_or_default());
+    result.push_str(
+        &diff
+            .unified_diff()
+            .context_radius(CONTEXT_LINES)
+            .header(&old_header, &new_header)
+            .to_string(),
+    );
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use sugars::hmap;
+
+    use super::*;
+    use crate::helpers::get_temp_dir;
+
+    #[test]
+    fn test_read_content() -> Result<()> {
+        // Arrange
+        let temp_dir = get_temp_dir(hmap! {
+            "text.txt" => "Hello, World!",
+            "binary.bin" => "Hello\0World",
+            "large.txt" => "0123456789",
+        });
+
+        // Act
+        let text = Content::read(&temp_dir.path().join("text.txt"), 100)?;
+        let binary = Content::read(&temp_dir.path().join("binary.bin"), 100)?;
+        let large = Content::read(&temp_dir.path().join("large.txt"), 5)?;
+
+        // Assert
+        assert_eq!(text, Content::Text("Hello, World!".to_string()));
+        assert_eq!(binary, Content::Binary);
+        assert_eq!(large, Content::TooLarge);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unified_diff_modified() {
+        assert_eq!(
+            unified_diff("dir/file.txt", Some("a\nb\nc\n"), Some("a\nB\nc\n")),
+            "diff --git a/dir/file.txt b/dir/file.txt
+--- a/dir/file.txt
++++ b/dir/file.txt
+@@ -1,3 +1,3 @@
+ a
+-b
++B
+ c
+"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_created() {
+        assert_eq!(
+            unified_diff("file.txt", None, Some("a\n")),
+            "diff --git a/file.txt b/file.txt
+new file mode 100644
+--- /dev/null
++++ b/file.txt
+@@ -0,0 +1 @@
++a
+"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_deleted() {
+        assert_eq!(
+            unified_diff("file.txt", Some("a"), None),
+            "diff --git a/file.txt b/file.txt
+deleted file mode 100644
+--- a/file.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-a
+\\ No newline at end of file
+"
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use anyhow::{Result, anyhow, bail};
+
+/// Parse a duration such as `30s`, `500ms`, `1.5m` or `1h`. A number without unit is in seconds.
+pub(crate) fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value = value
+        .parse::<f64>()
+        .map_err(|_| anyhow!("Invalid duration: `{s}`"))?;
+    let seconds = match unit.trim() {
+        "ms" => value / 1000.0,
+        "" | "s" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        unit => bail!("Unknown unit of duration: `{unit}`, expected one of `ms`, `s`, `m` or `h`"),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|_| anyhow!("Invalid duration: `{s}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("30", Duration::from_secs(30))]
+    #[case("30s", Duration::from_secs(30))]
+    #[case("500ms", Duration::from_millis(500))]
+    #[case("1.5m", Duration::from_secs(90))]
+    #[case("1h", Duration::from_secs(3600))]
+    fn test_parse_duration(#[case] s: &str, #[case] expected: Duration) -> Result<()> {
+        assert_eq!(parse_duration(s)?, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("", "Invalid duration: ``")]
+    #[case("s", "Invalid duration: `s`")]
+    #[case(
+        "10d",
+        "Unknown unit of duration: `d`, expected one of `ms`, `s`, `m` or `h`"
+    )]
+    fn test_parse_duration_error(#[case] s: &str, #[case] expected: &str) {
+        assert_eq!(parse_duration(s).unwrap_err().to_string(), expected);
+    }
+}
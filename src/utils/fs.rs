@@ -1,12 +1,12 @@
-use std::{fs::create_dir_all,
+use std::{fs::{copy, create_dir_all, read_dir, remove_file, rename, symlink_metadata},
           path::{Path, PathBuf}};
 
 use anyhow::Result;
-use glob::glob;
+use glob::{MatchOptions, Pattern, glob};
 
-/// Create the file if it does not exist, including its parent directories.
-pub(crate) fn touch_file(path: &Path) -> Result<()> {
-    log::trace!("Touching file: {}", path.display());
+/// Create the file with given content if it does not exist, including its parent directories.
+pub(crate) fn create_file(path: &Path, content: &str) -> Result<()> {
+    log::trace!("Creating file: {}", path.display());
     if path.exists() {
         log::trace!("File already exists: {}", path.display());
         return Ok(());
@@ -16,12 +16,64 @@ pub(crate) fn touch_file(path: &Path) -> Result<()> {
         path.parent()
             .expect("Failed to get parent directory for file creation."),
     )?;
-    std::fs::File::create(path)?;
+    std::fs::write(path, content)?;
     log::debug!("Created file: {}", path.display());
 
     Ok(())
 }
 
+/// Move the file to the given path, creating its parent directories.
+pub(crate) fn move_file(from: &Path, to: &Path) -> Result<()> {
+    log::trace!("Moving file {} to {}", from.display(), to.display());
+    create_dir_all(
+        to.parent()
+            .expect("Failed to get parent directory for file move."),
+    )?;
+    rename(from, to)?;
+    log::debug!("Moved file {} to {}", from.display(), to.display());
+
+    Ok(())
+}
+
+/// Copy the directory recursively, preserving symbolic links where supported.
+pub(crate) fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    log::trace!("Copying directory {} to {}", from.display(), to.display());
+    create_dir_all(to)?;
+    for entry in read_dir(from)? {
+        let entry = entry?;
+        let (source, destination) = (entry.path(), to.join(entry.file_name()));
+        if symlink_metadata(&source)?.is_dir() {
+            copy_dir(&source, &destination)?;
+        } else {
+            copy_file(&source, &destination)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy the file, creating its parent directories. Symbolic links are copied as links where
+/// supported, and a link at the destination is replaced rather than written through.
+pub(crate) fn copy_file(from: &Path, to: &Path) -> Result<()> {
+    log::trace!("Copying file {} to {}", from.display(), to.display());
+    if let Some(parent) = to.parent() {
+        create_dir_all(parent)?;
+    }
+    let is_symlink = |path: &Path| symlink_metadata(path).is_ok_and(|m| m.is_symlink());
+    #[cfg(unix)]
+    if is_symlink(from) {
+        if symlink_metadata(to).is_ok() {
+            remove_file(to)?;
+        }
+        std::os::unix::fs::symlink(std::fs::read_link(from)?, to)?;
+        return Ok(());
+    }
+    if is_symlink(to) {
+        remove_file(to)?;
+    }
+    copy(from, to)?;
+    Ok(())
+}
+
 /// List files in the `from` directory based on the include and exclude patterns.
 pub(crate) fn list_files(from: &Path, include: &[String], exclude: &[String]) -> Vec<PathBuf> {
     log::trace!(
@@ -63,16 +115,65 @@ pub(crate) fn expand_glob(from: &Path, patterns: &[String]) -> Vec<PathBuf> {
         .collect()
 }
 
+/// Split the file name into the base name and all extensions, such as `foo` and `test.ts` for
+/// `foo.test.ts`. A leading dot is part of the base name, as in `.eslintrc.json`.
+pub(crate) fn split_extensions(filename: &str) -> (&str, &str) {
+    let offset = if filename.starts_with('.') { 1 } else { 0 };
+    match filename[offset..].find('.') {
+        Some(index) => (&filename[..offset + index], &filename[offset + index + 1..]),
+        None => (filename, ""),
+    }
+}
+
+/// Check whether the given string contains glob special characters.
+pub(crate) fn is_glob_pattern(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+/// Check whether the relative path matches any of the include patterns and none of the exclude
+/// patterns, the same way as [`list_files`] does but without touching the file system, so paths
+/// of deleted files can be matched too.
+pub(crate) fn matches_globs(path: &Path, include: &[String], exclude: &[String]) -> Result<bool> {
+    let options = MatchOptions {
+        require_literal_separator: true,
+        ..MatchOptions::new()
+    };
+    let matches_any = |patterns: &[String]| -> Result<bool> {
+        for pattern in patterns {
+            if Pattern::new(pattern)?.matches_path_with(path, options) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    };
+    Ok(matches_any(include)? && !matches_any(exclude)?)
+}
+
+/// Deepest directory containing all of the given absolute paths.
+pub(crate) fn common_ancestor<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Option<PathBuf> {
+    let mut paths = paths.into_iter();
+    let mut ancestor = paths.next()?.to_path_buf();
+    for path in paths {
+        while !path.starts_with(&ancestor) {
+            if !ancestor.pop() {
+                return None;
+            }
+        }
+    }
+    Some(ancestor)
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
+    use rstest::rstest;
     use sugars::hmap;
 
     use super::*;
     use crate::helpers::get_temp_dir;
 
     #[test]
-    fn test_touch_file() -> Result<()> {
+    fn test_create_file() -> Result<()> {
         // Arrange
         let temp_dir = get_temp_dir(hmap! {});
         let dir_path = temp_dir.path();
@@ -80,7 +181,7 @@ mod tests {
         assert!(!file_path.exists());
 
         // Act
-        touch_file(&file_path)?;
+        create_file(&file_path, "")?;
 
         // Assert
         assert!(file_path.exists());
@@ -88,7 +189,7 @@ mod tests {
     }
 
     #[test]
-    fn test_touch_file_nested_directory() -> Result<()> {
+    fn test_create_file_nested_directory() -> Result<()> {
         // Arrange
         let temp_dir = get_temp_dir(hmap! {});
         let dir_path = temp_dir.path();
@@ -96,26 +197,101 @@ mod tests {
         assert!(!nested_file_path.exists());
 
         // Act
-        touch_file(&nested_file_path)?;
+        create_file(&nested_file_path, "Hello, World!")?;
 
         // Assert
-        assert!(nested_file_path.exists());
+        assert_eq!(std::fs::read_to_string(&nested_file_path)?, "Hello, World!");
         Ok(())
     }
 
     #[test]
-    fn test_touch_existing_file() -> Result<()> {
+    fn test_create_existing_file() -> Result<()> {
         // Arrange
-        let temp_dir = get_temp_dir(hmap! {"test.txt" => ""});
+        let temp_dir = get_temp_dir(hmap! {"test.txt" => "Original"});
         let dir_path = temp_dir.path();
         let file_path = dir_path.join("test.txt");
-        assert!(file_path.exists());
 
         // Act
-        touch_file(&file_path)?;
+        create_file(&file_path, "Overwritten")?;
 
         // Assert
-        assert!(file_path.exists());
+        assert_eq!(std::fs::read_to_string(&file_path)?, "Original");
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_dir() -> Result<()> {
+        // Arrange
+        let temp_dir = get_temp_dir(hmap! {
+            "from/file1.txt" => "Content of file 1",
+            "from/subdir/file2.txt" => "Content of file 2",
+            "from/empty/" => "",
+        });
+        let from = temp_dir.path().join("from");
+        let to = temp_dir.path().join("to");
+
+        // Act
+        copy_dir(&from, &to)?;
+
+        // Assert
+        assert_eq!(
+            std::fs::read_to_string(to.join("file1.txt"))?,
+            "Content of file 1"
+        );
+        assert_eq!(
+            std::fs::read_to_string(to.join("subdir/file2.txt"))?,
+            "Content of file 2"
+        );
+        assert!(to.join("empty").is_dir());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_file_symlink() -> Result<()> {
+        // Arrange
+        let temp_dir = get_temp_dir(hmap! {
+            "from/file.txt" => "Content of file",
+            "to/file.txt" => "Content of file",
+            "to/link.txt" => "Replaced by link",
+        });
+        let dir_path = temp_dir.path();
+        std::os::unix::fs::symlink("file.txt", dir_path.join("from/link.txt"))?;
+        std::os::unix::fs::symlink("file.txt", dir_path.join("to/replaced.txt"))?;
+
+        // Act
+        copy_file(
+            &dir_path.join("from/link.txt"),
+            &dir_path.join("to/link.txt"),
+        )?;
+        copy_file(
+            &dir_path.join("from/file.txt"),
+            &dir_path.join("to/replaced.txt"),
+        )?;
+
+        // Assert
+        assert_eq!(
+            std::fs::read_link(dir_path.join("to/link.txt"))?,
+            PathBuf::from("file.txt")
+        );
+        assert!(!symlink_metadata(dir_path.join("to/replaced.txt"))?.is_symlink());
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_file() -> Result<()> {
+        // Arrange
+        let temp_dir = get_temp_dir(hmap! {"test.txt" => "Hello, World!"});
+        let dir_path = temp_dir.path();
+        let file_path = dir_path.join("test.txt");
+        let new_path = dir_path.join("nested/dir/test.txt");
+
+        // Act
+        move_file(&file_path, &new_path)?;
+
+        // Assert
+        assert!(!file_path.exists());
+        assert_eq!(std::fs::read_to_string(&new_path)?, "Hello, World!");
         Ok(())
     }
 
@@ -193,6 +369,58 @@ mod tests {
         Ok(())
     }
 
+    #[rstest]
+    #[case("main.py", ("main", "py"))]
+    #[case("foo.test.ts", ("foo", "test.ts"))]
+    #[case("types.d.ts", ("types", "d.ts"))]
+    #[case("Dockerfile", ("Dockerfile", ""))]
+    #[case(".gitignore", (".gitignore", ""))]
+    #[case(".eslintrc.json", (".eslintrc", "json"))]
+    fn test_split_extensions(#[case] filename: &str, #[case] expected: (&str, &str)) {
+        assert_eq!(split_extensions(filename), expected);
+    }
+
+    #[test]
+    fn test_is_glob_pattern() {
+        assert!(is_glob_pattern("tests/**/test_*.py"));
+        assert!(is_glob_pattern("tests/test_?.py"));
+        assert!(is_glob_pattern("tests/test_[ab].py"));
+        assert!(!is_glob_pattern("tests/test_main.py"));
+    }
+
+    #[rstest]
+    #[case("main.py", true)]
+    #[case("src/main.py", true)]
+    #[case("src/main.rs", false)]
+    #[case("src/__init__.py", false)]
+    fn test_matches_globs(#[case] path: &str, #[case] expected: bool) -> Result<()> {
+        // Arrange
+        let include = vec!["**/*.py".to_string()];
+        let exclude = vec!["**/__init__.py".to_string()];
+
+        // Act
+        let result = matches_globs(Path::new(path), &include, &exclude)?;
+
+        // Assert
+        assert_eq!(result, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_matches_globs_literal_separator() -> Result<()> {
+        assert!(matches_globs(
+            Path::new("main.py"),
+            &["*.py".to_string()],
+            &[]
+        )?);
+        assert!(!matches_globs(
+            Path::new("src/main.py"),
+            &["*.py".to_string()],
+            &[]
+        )?);
+        Ok(())
+    }
+
     #[test]
     fn test_list_files_with_exclude() -> Result<()> {
         // Arrange
@@ -265,4 +493,17 @@ mod tests {
         assert_eq!(files, &[] as &[PathBuf]);
         Ok(())
     }
+
+    #[rstest]
+    #[case(&["/project/proto/gen"], Some("/project/proto/gen"))]
+    #[case(&["/project/proto/gen", "/project/docs/api"], Some("/project"))]
+    #[case(&["/project/web/src/client", "/project/web/src"], Some("/project/web/src"))]
+    #[case(&["/project/web", "/project/website"], Some("/project"))]
+    #[case(&[], None)]
+    fn test_common_ancestor(#[case] paths: &[&str], #[case] expected: Option<&str>) {
+        assert_eq!(
+            common_ancestor(paths.iter().map(Path::new)),
+            expected.map(PathBuf::from)
+        );
+    }
 }
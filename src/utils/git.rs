@@ -0,0 +1,110 @@
+use std::{convert::Infallible,
+          path::{Path, PathBuf},
+          process::Command,
+          str::FromStr};
+
+use anyhow::{Context, Result, bail};
+
+use crate::utils::{fs::matches_globs, manifest::Changes};
+
+/// Baseline in the git repository to compare the working tree against.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Baseline {
+    /// Contents staged in the index
+    Index,
+
+    /// Commit, branch or tag, such as `HEAD`
+    Ref(String),
+}
+
+impl FromStr for Baseline {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "git-index" => Self::Index,
+            s => Self::Ref(s.to_string()),
+        })
+    }
+}
+
+impl std::fmt::Display for Baseline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Index => write!(f, "git index"),
+            Self::Ref(reference) => write!(f, "`{reference}`"),
+        }
+    }
+}
+
+/// List files in the working tree under `dir` which differ from the baseline, with paths relative
+/// to `dir`. Untracked files, unless ignored by git, are listed as added. Only paths matching the
+/// include and exclude patterns are listed.
+pub(crate) fn changed_files(
+    dir: &Path,
+    baseline: &Baseline,
+    include: &[String],
+    exclude: &[String],
+) -> Result<Changes> {
+    let mut diff_args = vec!["diff", "--name-status", "--no-renames", "--relative", "-z"];
+    if let Baseline::Ref(reference) = baseline {
+        diff_args.push(reference);
+    }
+    diff_args.extend(["--", "."]);
+    let diff = git(dir, &diff_args)?;
+    let untracked = git(
+        dir,
+        &[
+            "ls-files",
+            "--others",
+            "--exclude-standard",
+            "-z",
+            "--",
+            ".",
+        ],
+    )?;
+
+    let mut changes = Changes::default();
+    let mut entries = diff.split('\0').filter(|s| !s.is_empty());
+    while let (Some(status), Some(path)) = (entries.next(), entries.next()) {
+        let path = PathBuf::from(path);
+        if !matches_globs(&path, include, exclude)? {
+            log::debug!(
+                "Ignoring changed file not matching patterns: {}",
+                path.display()
+            );
+            continue;
+        }
+        match status {
+            "A" => changes.added.push(path),
+            "D" => changes.removed.push(path),
+            _ => changes.modified.push(path),
+        }
+    }
+    for path in untracked.split('\0').filter(|s| !s.is_empty()) {
+        let path = PathBuf::from(path);
+        if matches_globs(&path, include, exclude)? {
+            changes.added.push(path);
+        }
+    }
+    changes.added.sort();
+    Ok(changes)
+}
+
+/// Run git in the directory, returning its standard output.
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    log::debug!("Running git in {}: {:?}", dir.display(), args);
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to run `git {}`: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
@@ -0,0 +1,231 @@
+use std::{fs::File,
+          io::Read,
+          num::NonZeroUsize,
+          path::{Path, PathBuf},
+          sync::atomic::{AtomicUsize, Ordering},
+          thread::{available_parallelism, scope}};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use xxhash_rust::xxh3::Xxh3;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Algorithm used to hash file contents.
+///
+/// All algorithms are fully specified and produce the same digest for the same input regardless of
+/// platform or program version, so digests can be recorded and compared later. Digests are
+/// formatted as lowercase hexadecimal.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum HashAlgorithm {
+    /// SHA-256 (FIPS 180-4), 256-bit cryptographic hash
+    Sha256,
+
+    /// BLAKE3, 256-bit cryptographic hash; fast on large files
+    #[default]
+    Blake3,
+
+    /// XXH3 128-bit variant, non-cryptographic hash; fastest, but not collision resistant
+    Xxh3,
+}
+
+/// Streaming hasher for the selected algorithm.
+pub(crate) enum Hasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+    Xxh3(Box<Xxh3>),
+}
+
+impl Hasher {
+    pub(crate) fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+            HashAlgorithm::Blake3 => Self::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgorithm::Xxh3 => Self::Xxh3(Box::new(Xxh3::new())),
+        }
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            Self::Xxh3(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Finish hashing and return the digest as lowercase hexadecimal.
+    pub(crate) fn finalize(self) -> String {
+        match self {
+            Self::Sha256(hasher) => to_hex(&hasher.finalize()),
+            Self::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+            // Canonical (big-endian) representation of the 128-bit digest
+            Self::Xxh3(hasher) => format!("{:032x}", hasher.digest128()),
+        }
+    }
+}
+
+/// Hash contents of the file, reading it in chunks.
+pub(crate) fn hash_file(path: &Path, algorithm: HashAlgorithm) -> Result<String> {
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut file = File::open(path)?;
+    loop {
+        let bytes_read = file.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+    Ok(hasher.finalize())
+}
+
+/// Hash contents of the bytes.
+pub(crate) fn hash_bytes(data: &[u8], algorithm: HashAlgorithm) -> String {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Hash the files with the given function, such as [`hash_file`], using `jobs` threads,
+/// defaulting to the available parallelism. Results are returned in the same order as the
+/// paths, regardless of the number of threads.
+pub(crate) fn hash_files<T: Send>(
+    paths: &[PathBuf],
+    jobs: Option<NonZeroUsize>,
+    hash: impl Fn(&Path) -> Result<T> + Sync,
+) -> Result<Vec<T>> {
+    let jobs = jobs
+        .or_else(|| available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get)
+        .min(paths.len());
+    if jobs <= 1 {
+        return paths.iter().map(|path| hash(path)).collect();
+    }
+
+    log::debug!("Hashing {} files using {jobs} threads", paths.len());
+    let next = AtomicUsize::new(0);
+    let mut digests = paths.iter().map(|_| None).collect::<Vec<_>>();
+    let hashed = scope(|scope| {
+        let workers = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut hashed = vec![];
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = paths.get(index) else {
+                            break;
+                        };
+                        hashed.push((index, hash(path)));
+                    }
+                    hashed
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("Hashing thread panicked"))
+            .collect::<Vec<_>>()
+    });
+    for (index, digest) in hashed {
+        digests[index] = Some(digest?);
+    }
+    Ok(digests
+        .into_iter()
+        .map(|digest| digest.expect("Every file should be hashed"))
+        .collect())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use sugars::hmap;
+
+    use super::*;
+    use crate::helpers::get_temp_dir;
+
+    #[rstest]
+    #[case(
+        HashAlgorithm::Sha256,
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    )]
+    #[case(
+        HashAlgorithm::Blake3,
+        "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+    )]
+    #[case(HashAlgorithm::Xxh3, "06b05ab6733a618578af5f94892f3950")]
+    fn test_hasher(#[case] algorithm: HashAlgorithm, #[case] expected: &str) {
+        // Arrange
+        let mut hasher = Hasher::new(algorithm);
+
+        // Act
+        hasher.update(b"a");
+        hasher.update(b"bc");
+
+        // Assert
+        assert_eq!(hasher.finalize(), expected);
+    }
+
+    #[rstest]
+    #[case(HashAlgorithm::Sha256)]
+    #[case(HashAlgorithm::Blake3)]
+    #[case(HashAlgorithm::Xxh3)]
+    fn test_hash_file(#[case] algorithm: HashAlgorithm) -> Result<()> {
+        // Arrange
+        let content = "0123456789".repeat(BUFFER_SIZE / 4);
+        let temp_dir = get_temp_dir(hmap! { "file.txt" => content.as_str() });
+        let mut hasher = Hasher::new(algorithm);
+        hasher.update(content.as_bytes());
+
+        // Act
+        let digest = hash_file(&temp_dir.path().join("file.txt"), algorithm)?;
+
+        // Assert
+        assert_eq!(digest, hasher.finalize());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_bytes() {
+        assert_eq!(
+            hash_bytes(b"abc", HashAlgorithm::Xxh3),
+            "06b05ab6733a618578af5f94892f3950"
+        );
+    }
+
+    #[test]
+    fn test_hash_files_parallel() -> Result<()> {
+        // Arrange
+        let files = (0..20)
+            .map(|i| (format!("file{i}.txt"), "content".repeat(i)))
+            .collect::<Vec<_>>();
+        let temp_dir = get_temp_dir(
+            files
+                .iter()
+                .map(|(name, content)| (name.as_str(), content.as_str()))
+                .collect(),
+        );
+        let paths = files
+            .iter()
+            .map(|(name, _)| temp_dir.path().join(name))
+            .collect::<Vec<_>>();
+
+        // Act
+        let hash = |path: &Path| hash_file(path, HashAlgorithm::Blake3);
+        let sequential = hash_files(&paths, NonZeroUsize::new(1), hash)?;
+        let parallel = hash_files(&paths, NonZeroUsize::new(4), hash)?;
+
+        // Assert
+        assert_eq!(sequential, parallel);
+        assert_eq!(sequential[3], hash_file(&paths[3], HashAlgorithm::Blake3)?);
+        Ok(())
+    }
+}
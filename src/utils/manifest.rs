@@ -0,0 +1,475 @@
+use std::{collections::{BTreeMap, BTreeSet},
+          fmt::Write,
+          fs::{Metadata, metadata, read, read_dir, read_link, symlink_metadata},
+          num::NonZeroUsize,
+          path::{MAIN_SEPARATOR, MAIN_SEPARATOR_STR, Path, PathBuf}};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::utils::{fs::{list_files, matches_globs},
+                   hash::{HashAlgorithm, Hasher, hash_bytes, hash_file, hash_files},
+                   normalize::Normalization};
+
+/// Placeholder hash for tracked directories, which have no content.
+const DIRECTORY_HASH: &str = "directory";
+
+#[derive(ValueEnum, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Track {
+    /// Empty directories, so creating or removing them is detected
+    Dirs,
+
+    /// Permission bits of files, such as the executable bit (Unix only)
+    Mode,
+
+    /// Symbolic links as links with their target, instead of following them
+    Symlinks,
+}
+
+/// Options to walk a directory and hash its files with.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HashOptions<'a> {
+    /// Glob patterns to include files.
+    pub(crate) include: &'a [String],
+
+    /// Glob patterns to exclude files.
+    pub(crate) exclude: &'a [String],
+
+    /// Additional attributes to track, besides file paths and contents.
+    pub(crate) track: &'a [Track],
+
+    /// Algorithm to hash file contents with.
+    pub(crate) algorithm: HashAlgorithm,
+
+    /// Number of threads to hash files with, defaulting to the available parallelism.
+    pub(crate) jobs: Option<NonZeroUsize>,
+
+    /// Rules to normalize contents of files with before hashing.
+    pub(crate) normalization: &'a Normalization,
+}
+
+/// Hashes of files in a directory.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct DirectoryHash {
+    /// Aggregate hash of all files, as a summary.
+    pub(crate) hash: String,
+
+    /// Each file, keyed by path relative to the directory. Tracked directories are keyed with a
+    /// trailing slash. Paths are saved with `/` as separator, to compare between platforms.
+    #[serde(
+        serialize_with = "serialize_files",
+        deserialize_with = "deserialize_files"
+    )]
+    pub(crate) files: BTreeMap<PathBuf, FileEntry>,
+}
+
+/// Serialize files keyed by paths with `/` as separator, regardless of the platform.
+fn serialize_files<S>(
+    files: &BTreeMap<PathBuf, FileEntry>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_map(
+        files
+            .iter()
+            .map(|(path, entry)| (path.to_string_lossy().replace(MAIN_SEPARATOR, "/"), entry)),
+    )
+}
+
+/// Deserialize files keyed by paths with `/` as separator into native paths.
+fn deserialize_files<'de, D>(deserializer: D) -> Result<BTreeMap<PathBuf, FileEntry>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(BTreeMap::<String, FileEntry>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(path, entry)| (PathBuf::from(path.replace('/', MAIN_SEPARATOR_STR)), entry))
+        .collect())
+}
+
+/// Hash and tracked attributes of a file.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq)]
+pub(crate) struct FileEntry {
+    /// Hash of the file contents, or of the link target for tracked symbolic links. Contents are
+    /// normalized first if any normalization rule applies to the file.
+    pub(crate) hash: String,
+
+    /// Hash of the file contents before normalization, if normalization changed them.
+    #[serde(skip)]
+    pub(crate) raw_hash: Option<String>,
+
+    /// Permission bits in octal, such as `755`, if modes are tracked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) mode: Option<String>,
+
+    /// Target of the symbolic link, if symbolic links are tracked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) symlink: Option<PathBuf>,
+}
+
+// Hashes before normalization are left out, so files only differing in what normalization removes
+// are equal.
+impl PartialEq for FileEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.mode == other.mode && self.symlink == other.symlink
+    }
+}
+
+impl FileEntry {
+    /// Hash of the file contents before normalization.
+    fn raw_hash(&self) -> &str {
+        self.raw_hash.as_deref().unwrap_or(&self.hash)
+    }
+
+    /// Describe how the tracked attributes differ from the other entry, such as `mode 644 -> 755`.
+    fn describe_changes(&self, after: &Self) -> Vec<String> {
+        let mut details = vec![];
+        if let (Some(before), Some(after)) = (&self.mode, &after.mode)
+            && before != after
+        {
+            details.push(format!("mode {before} -> {after}"));
+        }
+        match (&self.symlink, &after.symlink) {
+            (None, Some(target)) => {
+                details.push(format!("replaced with symlink to {}", target.display()))
+            }
+            (Some(_), None) => details.push("symlink replaced with file".to_string()),
+            (Some(before), Some(after)) if before != after => details.push(format!(
+                "symlink {} -> {}",
+                before.display(),
+                after.display()
+            )),
+            _ => {}
+        }
+        details
+    }
+}
+
+/// Files changed between two hashes of a directory.
+#[derive(Debug, Default)]
+pub(crate) struct Changes {
+    pub(crate) added: Vec<PathBuf>,
+    pub(crate) removed: Vec<PathBuf>,
+    pub(crate) modified: Vec<PathBuf>,
+
+    /// Changes of tracked attributes of modified files, such as mode, for the report.
+    pub(crate) details: BTreeMap<PathBuf, Vec<String>>,
+
+    /// Files which differ, but not after normalization, for the report. These are not changes.
+    pub(crate) normalized: Vec<PathBuf>,
+}
+
+impl Changes {
+    /// Compare file hashes before and after, listing added, removed and modified files.
+    pub(crate) fn between(before: &DirectoryHash, after: &DirectoryHash) -> Self {
+        let mut changes = Self::default();
+        for (path, entry) in &before.files {
+            match after.files.get(path) {
+                None => changes.removed.push(path.clone()),
+                Some(after_entry) if after_entry != entry => {
+                    changes.modified.push(path.clone());
+                    let details = entry.describe_changes(after_entry);
+                    if !details.is_empty() {
+                        changes.details.insert(path.clone(), details);
+                    }
+                }
+                Some(after_entry) if after_entry.raw_hash() != entry.raw_hash() => {
+                    changes.normalized.push(path.clone());
+                }
+                Some(_) => {}
+            }
+        }
+        for path in after.files.keys() {
+            if !before.files.contains_key(path) {
+                changes.added.push(path.clone());
+            }
+        }
+        changes
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    /// Changes undoing these changes, with added and removed files swapped.
+    pub(crate) fn reversed(&self) -> Self {
+        Self {
+            added: self.removed.clone(),
+            removed: self.added.clone(),
+            modified: self.modified.clone(),
+            details: BTreeMap::new(),
+            normalized: vec![],
+        }
+    }
+
+    /// Split changes into those of paths matching any of the patterns, and the rest. Files
+    /// differing only before normalization are left out.
+    pub(crate) fn partition(&self, patterns: &[String]) -> Result<(Self, Self)> {
+        let (mut matching, mut rest) = (Self::default(), Self::default());
+        for (paths, matching_paths, rest_paths) in [
+            (&self.added, &mut matching.added, &mut rest.added),
+            (&self.removed, &mut matching.removed, &mut rest.removed),
+            (&self.modified, &mut matching.modified, &mut rest.modified),
+        ] {
+            for path in paths {
+                let (paths, details) = if matches_globs(path, patterns, &[])? {
+                    (&mut *matching_paths, &mut matching.details)
+                } else {
+                    (&mut *rest_paths, &mut rest.details)
+                };
+                paths.push(path.clone());
+                if let Some(path_details) = self.details.get(path) {
+                    details.insert(path.clone(), path_details.clone());
+                }
+            }
+        }
+        Ok((matching, rest))
+    }
+
+    /// All changed paths, sorted.
+    pub(crate) fn paths(&self) -> BTreeSet<&PathBuf> {
+        self.added
+            .iter()
+            .chain(&self.removed)
+            .chain(&self.modified)
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Changes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (kind, paths) in [
+            ("added", &self.added),
+            ("removed", &self.removed),
+            ("modified", &self.modified),
+        ] {
+            for path in paths {
+                f.write_char('\n')?;
+                write!(f, "  {kind}: {}", path.display())?;
+                if kind == "modified"
+                    && let Some(details) = self.details.get(path)
+                {
+                    write!(f, " ({})", details.join(", "))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// NOTE: There is more performant library [merkle_hash](https://github.com/hristogochev/merkle_hash) exists,
+//       but using our version here for more control over hashing process (hasher, include/exclude patterns, etc.)
+pub(crate) fn calculate_directory_hash(
+    path: &Path,
+    options: &HashOptions,
+) -> Result<DirectoryHash> {
+    log::debug!(
+        "Calculating hash for directory: {}; options: {:?}",
+        path.display(),
+        options
+    );
+    let HashOptions {
+        include,
+        exclude,
+        track,
+        algorithm,
+        jobs,
+        normalization,
+    } = *options;
+    let mut files = BTreeMap::new();
+    let mut pending = vec![] as Vec<(PathBuf, PathBuf, Option<String>)>;
+    let mut symlinked_dirs = vec![] as Vec<PathBuf>;
+    for file_path in list_files(path, include, exclude) {
+        // Files within tracked symbolic links to directories are covered by the link itself
+        if symlinked_dirs.iter().any(|dir| file_path.starts_with(dir)) {
+            log::debug!("Skipping file within symlink: {}", file_path.display());
+            continue;
+        }
+
+        let relative_path = file_path.strip_prefix(path)?;
+        if track.contains(&Track::Symlinks) && symlink_metadata(&file_path)?.is_symlink() {
+            log::debug!("Tracking symlink: {}", file_path.display());
+            let target = read_link(&file_path)?;
+            let mut hasher = Hasher::new(algorithm);
+            hasher.update(target.to_string_lossy().as_bytes());
+            files.insert(
+                relative_path.to_path_buf(),
+                FileEntry {
+                    hash: hasher.finalize(),
+                    raw_hash: None,
+                    mode: None,
+                    symlink: Some(target),
+                },
+            );
+            if file_path.is_dir() {
+                symlinked_dirs.push(file_path);
+            }
+            continue;
+        }
+
+        if file_path.is_dir() {
+            if track.contains(&Track::Dirs) && read_dir(&file_path)?.next().is_none() {
+                log::debug!("Tracking empty directory: {}", file_path.display());
+                files.insert(
+                    PathBuf::from(format!("{}/", relative_path.display())),
+                    FileEntry {
+                        hash: DIRECTORY_HASH.to_string(),
+                        raw_hash: None,
+                        mode: None,
+                        symlink: None,
+                    },
+                );
+            } else {
+                log::debug!("Skipping directory: {}", file_path.display());
+            }
+            continue;
+        }
+
+        let mode = if track.contains(&Track::Mode) {
+            file_mode(&metadata(&file_path)?)
+        } else {
+            None
+        };
+        pending.push((relative_path.to_path_buf(), file_path, mode));
+    }
+
+    // Hash files concurrently, as it takes most of the time for large trees
+    let file_paths = pending
+        .iter()
+        .map(|(_, file_path, _)| file_path.clone())
+        .collect::<Vec<_>>();
+    let digests = hash_files(&file_paths, jobs, |file_path| {
+        let relative_path = file_path.strip_prefix(path)?;
+        if !normalization.applies_to(relative_path)? {
+            return Ok((hash_file(file_path, algorithm)?, None));
+        }
+        let content = read(file_path)?;
+        let normalized = normalization.normalize(relative_path, &content)?;
+        if normalized == content {
+            return Ok((hash_bytes(&content, algorithm), None));
+        }
+        log::debug!("Normalized contents of file: {}", file_path.display());
+        Ok((
+            hash_bytes(&normalized, algorithm),
+            Some(hash_bytes(&content, algorithm)),
+        ))
+    })?;
+    for ((relative_path, _, mode), (hash, raw_hash)) in pending.into_iter().zip(digests) {
+        files.insert(
+            relative_path,
+            FileEntry {
+                hash,
+                raw_hash,
+                mode,
+                symlink: None,
+            },
+        );
+    }
+
+    // Aggregate hash covers relative paths and file boundaries, so renaming files, creating empty
+    // files or moving content between files changes it. Each entry is fed as
+    // `<path>\0<digest>\n` with `/` as path separator, keeping the hash platform independent.
+    // Tracked attributes are inserted before the newline as `\0mode:<mode>` and
+    // `\0symlink:<target>`, so the hash is the same as before when nothing else is tracked.
+    let mut hasher = Hasher::new(algorithm);
+    for (relative_path, entry) in &files {
+        let relative_path = relative_path.to_string_lossy().replace(MAIN_SEPARATOR, "/");
+        hasher.update(relative_path.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(entry.hash.as_bytes());
+        if let Some(mode) = &entry.mode {
+            hasher.update(format!("\0mode:{mode}").as_bytes());
+        }
+        if let Some(target) = &entry.symlink {
+            hasher.update(format!("\0symlink:{}", target.to_string_lossy()).as_bytes());
+        }
+        hasher.update(b"\n");
+    }
+    Ok(DirectoryHash {
+        hash: hasher.finalize(),
+        files,
+    })
+}
+
+/// Permission bits of the file in octal.
+#[cfg(unix)]
+fn file_mode(metadata: &Metadata) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    Some(format!("{:03o}", metadata.permissions().mode() & 0o7777))
+}
+
+/// Permission bits are not available on this platform.
+#[cfg(not(unix))]
+fn file_mode(_metadata: &Metadata) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn entry(hash: &str) -> FileEntry {
+        FileEntry {
+            hash: hash.to_string(),
+            raw_hash: None,
+            mode: None,
+            symlink: None,
+        }
+    }
+
+    #[test]
+    fn test_serialize_files_separator() -> Result<()> {
+        // Arrange
+        let directory = DirectoryHash {
+            hash: "aggregate".to_string(),
+            files: BTreeMap::from([
+                (Path::new("subdir").join("file2.txt"), entry("file2")),
+                (PathBuf::from("subdir/"), entry(DIRECTORY_HASH)),
+            ]),
+        };
+
+        // Act
+        let value = serde_json::to_value(&directory)?;
+
+        // Assert
+        assert_eq!(
+            value,
+            json!({
+                "hash": "aggregate",
+                "files": {
+                    "subdir/": {"hash": "directory"},
+                    "subdir/file2.txt": {"hash": "file2"},
+                },
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_files_separator() -> Result<()> {
+        // Arrange
+        let value = json!({
+            "hash": "aggregate",
+            "files": {
+                "subdir/file2.txt": {"hash": "file2"},
+            },
+        });
+
+        // Act
+        let directory = serde_json::from_value::<DirectoryHash>(value)?;
+
+        // Assert
+        assert_eq!(
+            directory.files.keys().collect::<Vec<_>>(),
+            [&Path::new("subdir").join("file2.txt")]
+        );
+        Ok(())
+    }
+}
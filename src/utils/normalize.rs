@@ -0,0 +1,174 @@
+use std::{path::Path, slice::from_ref, str::FromStr};
+
+use anyhow::{Error, Result, anyhow};
+use regex::bytes::Regex;
+
+use crate::utils::{fs::matches_globs, semantic::parse};
+
+/// Rules to normalize contents of files before hashing, so differences which do not matter, such
+/// as line endings, are not detected as changes. Each rule applies to files matching its globs.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Normalization {
+    /// Patterns of files to convert CRLF line endings to LF in.
+    pub(crate) eol: Vec<String>,
+
+    /// Patterns of files to strip trailing whitespace of lines in.
+    pub(crate) trailing_whitespace: Vec<String>,
+
+    /// Lines to drop from files.
+    pub(crate) ignore_lines: Vec<IgnoreLine>,
+
+    /// Patterns of JSON, YAML or TOML files to compare by their parsed values, ignoring key order
+    /// and formatting. Other rules do not apply to these files, unless they fail to parse.
+    pub(crate) semantic: Vec<String>,
+}
+
+/// Lines matching the regular expression to drop from files matching the glob, given as
+/// `GLOB=REGEX`.
+#[derive(Clone, Debug)]
+pub(crate) struct IgnoreLine {
+    pub(crate) glob: String,
+    pub(crate) regex: Regex,
+}
+
+impl FromStr for IgnoreLine {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (glob, regex) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid line pattern `{s}`, expected `GLOB=REGEX`"))?;
+        Ok(Self {
+            glob: glob.to_string(),
+            regex: Regex::new(regex)?,
+        })
+    }
+}
+
+impl Normalization {
+    /// Check whether any rule applies to the file at the relative path.
+    pub(crate) fn applies_to(&self, path: &Path) -> Result<bool> {
+        Ok(matches_globs(path, &self.semantic, &[])?
+            || matches_globs(path, &self.eol, &[])?
+            || matches_globs(path, &self.trailing_whitespace, &[])?
+            || self.line_regexes(path)?.next().is_some())
+    }
+
+    /// Normalize contents of the file at the relative path, with the rules applying to it.
+    pub(crate) fn normalize(&self, path: &Path, content: &[u8]) -> Result<Vec<u8>> {
+        if matches_globs(path, &self.semantic, &[])? {
+            match parse(path, content) {
+                // Keys of objects are sorted, as `serde_json` keeps them in a sorted map
+                Ok(value) => return Ok(serde_json::to_vec(&value)?),
+                Err(err) => log::warn!(
+                    "Failed to parse {} to compare semantically, comparing contents instead: {err}",
+                    path.display()
+                ),
+            }
+        }
+        let eol = matches_globs(path, &self.eol, &[])?;
+        let trailing_whitespace = matches_globs(path, &self.trailing_whitespace, &[])?;
+        let regexes = self.line_regexes(path)?.collect::<Vec<_>>();
+
+        let mut normalized = Vec::with_capacity(content.len());
+        for line in content.split_inclusive(|&byte| byte == b'\n') {
+            let (mut body, mut ending) = match line {
+                [body @ .., b'\r', b'\n'] => (body, b"\r\n".as_slice()),
+                [body @ .., b'\n'] => (body, b"\n".as_slice()),
+                body => (body, b"".as_slice()),
+            };
+            if regexes.iter().any(|regex| regex.is_match(body)) {
+                continue;
+            }
+            if eol && ending == b"\r\n" {
+                ending = b"\n";
+            }
+            if trailing_whitespace {
+                body = body.trim_ascii_end();
+            }
+            normalized.extend_from_slice(body);
+            normalized.extend_from_slice(ending);
+        }
+        Ok(normalized)
+    }
+
+    /// Regular expressions of lines to drop from the file at the relative path.
+    fn line_regexes(&self, path: &Path) -> Result<impl Iterator<Item = &Regex>> {
+        let mut regexes = vec![];
+        for ignore_line in &self.ignore_lines {
+            if matches_globs(path, from_ref(&ignore_line.glob), &[])? {
+                regexes.push(&ignore_line.regex);
+            }
+        }
+        Ok(regexes.into_iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    fn normalization() -> Normalization {
+        Normalization {
+            eol: vec!["**/*.txt".to_string()],
+            trailing_whitespace: vec!["**/*.md".to_string()],
+            ignore_lines: vec!["**/*.ts=^// Generated at ".parse().unwrap()],
+            semantic: vec!["**/*.json".to_string()],
+        }
+    }
+
+    #[rstest]
+    #[case("file.txt", "a\r\nb \r\nc", "a\nb \nc")]
+    #[case("file.md", "a  \r\nb\t\nc ", "a\r\nb\nc")]
+    #[case(
+        "src/client.ts",
+        "// Generated at 2026-10-16T00:00:00Z\r\nexport {};\n",
+        "export {};\n"
+    )]
+    #[case("file.rs", "a \r\n", "a \r\n")]
+    #[case(
+        "package.json",
+        "{\n  \"b\": 1,\n  \"a\": [true]\n}\n",
+        r#"{"a":[true],"b":1}"#
+    )]
+    #[case("invalid.json", "{\n", "{\n")]
+    fn test_normalize(
+        #[case] path: &str,
+        #[case] content: &str,
+        #[case] expected: &str,
+    ) -> Result<()> {
+        // Arrange
+        let normalization = normalization();
+
+        // Act
+        let normalized = normalization.normalize(Path::new(path), content.as_bytes())?;
+
+        // Assert
+        assert_eq!(String::from_utf8(normalized)?, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("file.txt", true)]
+    #[case("docs/file.md", true)]
+    #[case("src/client.ts", true)]
+    #[case("package.json", true)]
+    #[case("file.rs", false)]
+    fn test_applies_to(#[case] path: &str, #[case] expected: bool) -> Result<()> {
+        assert_eq!(normalization().applies_to(Path::new(path))?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_ignore_line_error() {
+        assert_eq!(
+            "^// Generated"
+                .parse::<IgnoreLine>()
+                .unwrap_err()
+                .to_string(),
+            "Invalid line pattern `^// Generated`, expected `GLOB=REGEX`"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use std::{path::Path, str::from_utf8};
+
+use anyhow::{Result, bail};
+use serde_json::Value;
+
+/// Format of data files which can be compared semantically.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    /// Detect the format from the extension of the file.
+    pub(crate) fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+}
+
+/// Parse contents of the file into a value, so that key order and formatting do not matter.
+pub(crate) fn parse(path: &Path, content: &[u8]) -> Result<Value> {
+    let Some(format) = Format::from_path(path) else {
+        bail!(
+            "Unsupported format of file: {}, expected JSON, YAML or TOML",
+            path.display()
+        );
+    };
+    Ok(match format {
+        Format::Json => serde_json::from_slice(content)?,
+        Format::Yaml => serde_norway::from_slice(content)?,
+        Format::Toml => toml::from_str(from_utf8(content)?)?,
+    })
+}
+
+/// Describe how the value differs from the other value, with paths of changed keys such as
+/// ``changed `dependencies.serde` ``.
+pub(crate) fn describe_changes(before: &Value, after: &Value) -> Vec<String> {
+    let mut details = vec![];
+    describe_changes_at("", before, after, &mut details);
+    details
+}
+
+fn describe_changes_at(path: &str, before: &Value, after: &Value, details: &mut Vec<String>) {
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            for (key, before_value) in before {
+                let key_path = join_key(path, key);
+                match after.get(key) {
+                    Some(after_value) => {
+                        describe_changes_at(&key_path, before_value, after_value, details)
+                    }
+                    None => details.push(format!("removed `{key_path}`")),
+                }
+            }
+            for key in after.keys().filter(|key| !before.contains_key(*key)) {
+                details.push(format!("added `{}`", join_key(path, key)));
+            }
+        }
+        (Value::Array(before), Value::Array(after)) => {
+            for (index, (before_value, after_value)) in before.iter().zip(after).enumerate() {
+                describe_changes_at(
+                    &format!("{path}[{index}]"),
+                    before_value,
+                    after_value,
+                    details,
+                );
+            }
+            for index in after.len()..before.len() {
+                details.push(format!("removed `{path}[{index}]`"));
+            }
+            for index in before.len()..after.len() {
+                details.push(format!("added `{path}[{index}]`"));
+            }
+        }
+        (before, after) if before != after => {
+            if path.is_empty() {
+                details.push("changed root value".to_string());
+            } else {
+                details.push(format!("changed `{path}`"));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Path of the key within the object at the path, such as `dependencies.serde`.
+fn join_key(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use serde_json::json;
+
+    use super::*;
+
+    #[rstest]
+    #[case("package.json", r#"{"b": [1, 2], "a": {"c": true}}"#)]
+    #[case("openapi.yaml", "a:\n  c: true\nb:\n  - 1\n  - 2\n")]
+    #[case("Cargo.toml", "b = [1, 2]\n\n[a]\nc = true\n")]
+    fn test_parse(#[case] path: &str, #[case] content: &str) -> Result<()> {
+        assert_eq!(
+            parse(Path::new(path), content.as_bytes())?,
+            json!({"a": {"c": true}, "b": [1, 2]})
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_unsupported_format() {
+        assert_eq!(
+            parse(Path::new("file.txt"), b"").unwrap_err().to_string(),
+            "Unsupported format of file: file.txt, expected JSON, YAML or TOML"
+        );
+    }
+
+    #[test]
+    fn test_describe_changes() {
+        // Arrange
+        let before = json!({
+            "version": "1.0.0",
+            "scripts": {"test": "jest"},
+            "files": ["dist", "src"],
+        });
+        let after = json!({
+            "version": "1.0.1",
+            "scripts": {"lint": "eslint"},
+            "files": ["dist"],
+        });
+
+        // Act
+        let details = describe_changes(&before, &after);
+
+        // Assert
+        assert_eq!(
+            details,
+            [
+                "removed `files[1]`",
+                "removed `scripts.test`",
+                "added `scripts.lint`",
+                "changed `version`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_describe_changes_root() {
+        assert_eq!(
+            describe_changes(&json!([1]), &json!({})),
+            ["changed root value"]
+        );
+    }
+}
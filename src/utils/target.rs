@@ -0,0 +1,135 @@
+use std::str::FromStr;
+
+use anyhow::{Error, bail};
+
+/// Target directory with its own include and exclude patterns, given as
+/// `PATH[;include=GLOB,...][;exclude=GLOB,...]`. Patterns not given fall back to the ones given
+/// for all targets.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct TargetSpec {
+    /// Path to the target directory, as given.
+    pub(crate) path: String,
+
+    /// Glob patterns to include files from the target, if given.
+    pub(crate) include: Option<Vec<String>>,
+
+    /// Glob patterns to exclude files from the target, if given.
+    pub(crate) exclude: Option<Vec<String>>,
+}
+
+impl TargetSpec {
+    /// Patterns to include files, falling back to the given default.
+    pub(crate) fn include<'a>(&'a self, default: &'a [String]) -> &'a [String] {
+        self.include.as_deref().unwrap_or(default)
+    }
+
+    /// Patterns to exclude files, falling back to the given default.
+    pub(crate) fn exclude<'a>(&'a self, default: &'a [String]) -> &'a [String] {
+        self.exclude.as_deref().unwrap_or(default)
+    }
+}
+
+impl FromStr for TargetSpec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(';');
+        let path = parts.next().unwrap_or_default().trim();
+        if path.is_empty() {
+            bail!("Empty path in target `{s}`");
+        }
+        let mut spec = Self {
+            path: path.to_string(),
+            include: None,
+            exclude: None,
+        };
+        for option in parts.filter(|part| !part.trim().is_empty()) {
+            let Some((key, value)) = option.split_once('=') else {
+                bail!("Invalid option `{option}` in target `{s}`, expected `KEY=VALUE`");
+            };
+            let patterns = value
+                .split(',')
+                .map(|pattern| pattern.trim().to_string())
+                .filter(|pattern| !pattern.is_empty())
+                .collect();
+            match key.trim() {
+                "include" => spec.include = Some(patterns),
+                "exclude" => spec.exclude = Some(patterns),
+                key => bail!(
+                    "Unknown option `{key}` in target `{s}`, expected one of `include` or `exclude`"
+                ),
+            }
+        }
+        Ok(spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("proto/gen", "proto/gen", None, None)]
+    #[case(
+        "proto/gen;include=**/*.go,**/*.ts",
+        "proto/gen",
+        Some(vec!["**/*.go", "**/*.ts"]),
+        None
+    )]
+    #[case(
+        "docs/api;exclude=**/*.log;include=**/*.md",
+        "docs/api",
+        Some(vec!["**/*.md"]),
+        Some(vec!["**/*.log"])
+    )]
+    #[case("web/src/client;", "web/src/client", None, None)]
+    fn test_parse_target_spec(
+        #[case] s: &str,
+        #[case] path: &str,
+        #[case] include: Option<Vec<&str>>,
+        #[case] exclude: Option<Vec<&str>>,
+    ) -> anyhow::Result<()> {
+        let to_strings = |patterns: Vec<&str>| patterns.into_iter().map(String::from).collect();
+        assert_eq!(
+            s.parse::<TargetSpec>()?,
+            TargetSpec {
+                path: path.to_string(),
+                include: include.map(to_strings),
+                exclude: exclude.map(to_strings),
+            }
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(";include=**/*", "Empty path in target `;include=**/*`")]
+    #[case(
+        "dir;include",
+        "Invalid option `include` in target `dir;include`, expected `KEY=VALUE`"
+    )]
+    #[case(
+        "dir;track=mode",
+        "Unknown option `track` in target `dir;track=mode`, expected one of `include` or `exclude`"
+    )]
+    fn test_parse_target_spec_error(#[case] s: &str, #[case] expected: &str) {
+        assert_eq!(s.parse::<TargetSpec>().unwrap_err().to_string(), expected);
+    }
+
+    #[test]
+    fn test_target_spec_fallback() -> anyhow::Result<()> {
+        // Arrange
+        let spec = "dir;exclude=**/*.log".parse::<TargetSpec>()?;
+        let default = vec!["**/*".to_string()];
+
+        // Act
+        let include = spec.include(&default);
+        let exclude = spec.exclude(&default);
+
+        // Assert
+        assert_eq!(include, ["**/*"]);
+        assert_eq!(exclude, ["**/*.log"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, bail};
+
+/// Render the template, substituting `{name}` placeholders with values from `vars`.
+///
+/// Placeholders can be followed by filters separated by `|`, with arguments separated by `:`,
+/// such as `{stem|snake}` or `{relative_from|replace:/:.}`. Filters are applied from left to right.
+///
+/// Available filters:
+///
+/// - `lower`, `upper`: convert to lowercase or uppercase
+///
+/// - `snake`, `kebab`, `pascal`, `camel`: convert the case of words
+///
+/// - `replace:<from>:<to>`: replace all occurrences of `from` with `to`
+///
+/// - `strip_prefix:<prefix>`, `strip_suffix:<suffix>`: remove the prefix or suffix if present
+///
+/// - `default:<value>`: use the value if the variable is missing
+///
+/// Literal braces must be escaped by doubling them (`{{` and `}}`).
+pub(crate) fn render<V: AsRef<str>>(template: &str, vars: &HashMap<String, V>) -> Result<String> {
+    substitute(template, |placeholder| {
+        render_placeholder(placeholder, vars)
+    })
+}
+
+/// Render the template like [`render`], passing substituted values through `escape`, such as
+/// [`glob::Pattern::escape`] to keep them literal in a glob pattern.
+pub(crate) fn render_escaped<V: AsRef<str>>(
+    template: &str,
+    vars: &HashMap<String, V>,
+    escape: impl Fn(&str) -> String,
+) -> Result<String> {
+    substitute(template, |placeholder| {
+        Ok(escape(&render_placeholder(placeholder, vars)?))
+    })
+}
+
+/// Literal text of the template, with placeholders removed, to inspect what the template itself
+/// contains regardless of substituted values.
+pub(crate) fn literal_text(template: &str) -> Result<String> {
+    substitute(template, |_| Ok(String::new()))
+}
+
+/// Substitute each placeholder of the template, without the surrounding braces, with the result
+/// of the function.
+fn substitute(
+    template: &str,
+    mut substitute_placeholder: impl FnMut(&str) -> Result<String>,
+) -> Result<String> {
+    let mut result = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => bail!("Unclosed placeholder in template: {{{placeholder}"),
+                    }
+                }
+                result.push_str(&substitute_placeholder(&placeholder)?);
+            }
+            '}' => bail!("Unmatched `}}` in template, use `}}}}` to escape it"),
+            c => result.push(c),
+        }
+    }
+    Ok(result)
+}
+
+/// Render a single placeholder, without the surrounding braces.
+fn render_placeholder<V: AsRef<str>>(
+    placeholder: &str,
+    vars: &HashMap<String, V>,
+) -> Result<String> {
+    let mut parts = placeholder.split('|');
+    let name = parts.next().unwrap_or_default().trim();
+    if name.is_empty() {
+        bail!("Empty placeholder in template: {{{placeholder}}}");
+    }
+
+    let mut value = vars.get(name).map(|v| v.as_ref().to_string());
+    for filter in parts {
+        let mut args = filter.split(':');
+        let filter_name = args.next().unwrap_or_default().trim();
+        let args = args.collect::<Vec<_>>();
+        if filter_name == "default" {
+            if value.is_none() {
+                value = Some(args.join(":"));
+            }
+            continue;
+        }
+        let Some(current) = value else {
+            bail!("Missing variable `{name}` in template");
+        };
+        value = Some(apply_filter(&current, filter_name, &args)?);
+    }
+
+    value.ok_or_else(|| anyhow::anyhow!("Missing variable `{name}` in template"))
+}
+
+/// Apply the named filter to the value.
+fn apply_filter(value: &str, filter: &str, args: &[&str]) -> Result<String> {
+    let result = match (filter, args) {
+        ("lower", []) => value.to_lowercase(),
+        ("upper", []) => value.to_uppercase(),
+        ("snake", []) => split_words(value).join("_").to_lowercase(),
+        ("kebab", []) => split_words(value).join("-").to_lowercase(),
+        ("pascal", []) => split_words(value).iter().map(|w| capitalize(w)).collect(),
+        ("camel", []) => split_words(value)
+            .iter()
+            .enumerate()
+            .map(|(i, w)| {
+                if i == 0 {
+                    w.to_lowercase()
+                } else {
+                    capitalize(w)
+                }
+            })
+            .collect(),
+        ("replace", [from, to]) => value.replace(from, to),
+        ("strip_prefix", [prefix]) => value.strip_prefix(prefix).unwrap_or(value).to_string(),
+        ("strip_suffix", [suffix]) => value.strip_suffix(suffix).unwrap_or(value).to_string(),
+        ("lower" | "upper" | "snake" | "kebab" | "pascal" | "camel", _)
+        | ("replace" | "strip_prefix" | "strip_suffix", _) => {
+            bail!("Invalid number of arguments for filter `{filter}`: {args:?}")
+        }
+        _ => bail!("Unknown filter in template: `{filter}`"),
+    };
+    Ok(result)
+}
+
+/// Split the value into words on non-alphanumeric characters and case boundaries,
+/// e.g. `HTTPServer_config` into `HTTP`, `Server` and `config`.
+fn split_words(value: &str) -> Vec<String> {
+    let mut words = vec![] as Vec<String>;
+    for part in value.split(|c: char| !c.is_alphanumeric()) {
+        let chars = part.chars().collect::<Vec<_>>();
+        let mut word = String::new();
+        for (i, &c) in chars.iter().enumerate() {
+            let prev = i.checked_sub(1).map(|i| chars[i]);
+            let next = chars.get(i + 1);
+            let boundary = c.is_uppercase()
+                && prev
+                    .is_some_and(|p| !p.is_uppercase() || next.is_some_and(|n| n.is_lowercase()));
+            if boundary && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            word.push(c);
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+    }
+    words
+}
+
+/// Uppercase the first character and lowercase the rest.
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use sugars::hmap;
+
+    use super::*;
+
+    #[rstest]
+    #[case("{to}/test_{stem}.py", "tests/test_UserProfile.py")]
+    #[case("{{{stem}}}", "{UserProfile}")]
+    #[case("{stem|snake}", "user_profile")]
+    #[case("{stem|kebab}.test.ts", "user-profile.test.ts")]
+    #[case("{stem|camel}", "userProfile")]
+    #[case("{stem|lower}", "userprofile")]
+    #[case("{stem|upper}", "USERPROFILE")]
+    #[case("{module|pascal}", "FooBar")]
+    #[case("{module|replace:B:_b}", "foo_bar")]
+    #[case("{relative_from|strip_prefix:pkg/}", "utils/slack")]
+    #[case("{relative_from|strip_suffix:/slack}", "pkg/utils")]
+    #[case("{relative_from|strip_prefix:pkg/|replace:/:.}", "utils.slack")]
+    #[case("{missing|default:none}", "none")]
+    #[case("{missing|default:a:b|upper}", "A:B")]
+    #[case("{stem|default:none}", "UserProfile")]
+    fn test_render(#[case] template: &str, #[case] expected: &str) -> Result<()> {
+        // Arrange
+        let vars = hmap! {
+            "to".to_string() => "tests",
+            "stem".to_string() => "UserProfile",
+            "module".to_string() => "fooBar",
+            "relative_from".to_string() => "pkg/utils/slack",
+        };
+
+        // Act
+        let result = render(template, &vars)?;
+
+        // Assert
+        assert_eq!(result, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("{missing}", "Missing variable `missing` in template")]
+    #[case("{stem|unknown}", "Unknown filter in template: `unknown`")]
+    #[case(
+        "{stem|replace:a}",
+        "Invalid number of arguments for filter `replace`: [\"a\"]"
+    )]
+    #[case("{stem", "Unclosed placeholder in template: {stem")]
+    #[case("stem}", "Unmatched `}` in template, use `}}` to escape it")]
+    fn test_render_error(#[case] template: &str, #[case] expected: &str) {
+        // Arrange
+        let vars = hmap! { "stem".to_string() => "main" };
+
+        // Act
+        let result = render(template, &vars);
+
+        // Assert
+        assert_eq!(result.unwrap_err().to_string(), expected);
+    }
+
+    #[test]
+    fn test_render_escaped() -> Result<()> {
+        // Arrange
+        let vars = hmap! {
+            "to".to_string() => "tests",
+            "stem".to_string() => "[id]",
+        };
+
+        // Act
+        let result = render_escaped("{to}/**/{stem}.test.tsx", &vars, glob::Pattern::escape)?;
+
+        // Assert
+        assert_eq!(result, "tests/**/[[]id[]].test.tsx");
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("{to}/pages/{stem}.test.tsx", "/pages/.test.tsx")]
+    #[case("{to}/**/test_{stem}_*.py", "/**/test__*.py")]
+    #[case("{{{stem|replace:*:_}}}", "{}")]
+    fn test_literal_text(#[case] template: &str, #[case] expected: &str) -> Result<()> {
+        assert_eq!(literal_text(template)?, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("UserProfile", &["User", "Profile"])]
+    #[case("user_profile", &["user", "profile"])]
+    #[case("user-profile.test", &["user", "profile", "test"])]
+    #[case("HTTPServer", &["HTTP", "Server"])]
+    #[case("fooBar2Baz", &["foo", "Bar2", "Baz"])]
+    #[case("__init__", &["init"])]
+    fn test_split_words(#[case] value: &str, #[case] expected: &[&str]) {
+        assert_eq!(split_words(value), expected);
+    }
+}
@@ -1,2 +1,3 @@
 mod test_assert_diff;
 mod test_check_file_pair;
+mod test_snapshot;
@@ -1,10 +1,16 @@
-use std::collections::HashMap;
+use std::{collections::HashMap,
+          fs::{read_to_string, write},
+          path::Path,
+          process::Command,
+          thread::sleep,
+          time::Duration};
 
 use anyhow::Result;
 use insta::assert_snapshot;
+use rstest::rstest;
 use sugars::hmap;
 
-use crate::{helpers::{get_cmd, get_temp_dir, normalize_console_output, parse_output},
+use crate::{helpers::{get_cmd, get_temp_dir, list_dir, normalize_console_output, parse_output},
             to_str};
 
 /// Test command with an empty directory.
@@ -332,3 +338,1460 @@ fn test_on_command_error_ignore() -> Result<()> {
     assert_eq!(stderr, "");
     Ok(())
 }
+
+/// Test that every added, removed and modified file is listed in the error.
+#[test]
+fn test_changes_report() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+        "target/file2.txt" => "Content of file 2",
+        "target/subdir/file3.txt" => "Content of file 3",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .arg("--")
+        .args([
+            "sh",
+            "-c",
+            r#"
+echo 'New file content' > target/subdir/new_file.txt;
+echo 'Modified content' > target/file1.txt;
+rm target/file2.txt;
+"#
+            .trim(),
+        ])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stderr,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    Ok(())
+}
+
+/// Test that structure-only changes are detected; renaming a file, creating an empty file
+/// and moving content from one file to another.
+#[rstest]
+#[case("rename", "mv target/file1.txt target/renamed.txt")]
+#[case("empty_file", "touch target/empty.txt")]
+#[case(
+    "move_content",
+    "printf 'Content of file 1Content' > target/file1.txt; printf ' of file 2' > target/file2.txt"
+)]
+fn test_changes_structure(#[case] name: &str, #[case] script: &str) -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+        "target/file2.txt" => "Content of file 2",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .arg("--")
+        .args(["sh", "-c", script])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_snapshot!(
+        format!("changes_structure_{name}"),
+        normalize_console_output(
+            stderr,
+            hmap! {
+                to_str!(dir_path) => "<temp_dir>"
+            }
+        )
+    );
+    Ok(())
+}
+
+/// Test for `--track dirs` option, which detects creation of empty directories.
+#[test]
+fn test_track_dirs() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+        "target/empty/" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .args(["--track", "dirs"])
+        .arg("--")
+        .args(["sh", "-c", "mkdir target/new_dir && rmdir target/empty"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stderr,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    Ok(())
+}
+
+/// Test hashing with each algorithm. Digests are stable, so they must never change.
+#[rstest]
+#[case("sha256")]
+#[case("blake3")]
+#[case("xxh3")]
+fn test_hash_algorithm(#[case] algorithm: &str) -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+        "target/subdir/file2.txt" => "Content of file 2",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .args(["--hash-algorithm", algorithm])
+        .arg("--")
+        .args(["true"])
+        .assert();
+
+    // Assert
+    let result = assert.success().code(0);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(
+        format!("hash_algorithm_{algorithm}"),
+        normalize_console_output(
+            stdout,
+            hmap! {
+                to_str!(dir_path) => "<temp_dir>"
+            }
+        )
+    );
+    assert_eq!(stderr, "");
+    Ok(())
+}
+
+/// Test showing diffs of changed files, including binary files which cannot be diffed.
+#[test]
+fn test_show_diff() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "line 1\nline 2\nline 3\n",
+        "target/subdir/file2.txt" => "Content of file 2",
+        "target/image.bin" => "\0binary",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .arg("--show-diff")
+        .arg("--")
+        .args([
+            "sh",
+            "-c",
+            "printf 'line 1\\nline two\\nline 3\\n' > target/file1.txt \
+             && rm target/subdir/file2.txt \
+             && echo 'Content of file 3' > target/file3.txt \
+             && printf '\\0changed' > target/image.bin",
+        ])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, _) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    Ok(())
+}
+
+/// Test truncating long diffs to the maximum number of lines.
+#[test]
+fn test_show_diff_max_lines() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "line 1\n",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .arg("--show-diff")
+        .args(["--diff-max-lines", "5"])
+        .arg("--")
+        .args(["sh", "-c", "seq 1 10 > target/file1.txt"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, _) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    Ok(())
+}
+
+/// Test writing the patch of changed files, which reproduces the changes when applied.
+#[test]
+fn test_patch_out() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "line 1\nline 2\nline 3\n",
+        "target/subdir/file2.txt" => "Content of file 2",
+        "original/file1.txt" => "line 1\nline 2\nline 3\n",
+        "original/subdir/file2.txt" => "Content of file 2",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .args(["--patch-out", "changes.patch"])
+        .arg("--")
+        .args([
+            "sh",
+            "-c",
+            "printf 'line 1\\nline two\\nline 3\\n' > target/file1.txt \
+             && rm target/subdir/file2.txt \
+             && echo 'Content of file 3' > target/file3.txt",
+        ])
+        .assert();
+
+    // Assert
+    assert.failure().code(1);
+    assert_snapshot!(read_to_string(dir_path.join("changes.patch"))?);
+    let status = Command::new("git")
+        .current_dir(dir_path.join("original"))
+        .args(["apply", to_str!(dir_path.join("changes.patch"))])
+        .status()?;
+    assert!(status.success());
+    assert_eq!(
+        list_dir(&dir_path.join("original")),
+        list_dir(&dir_path.join("target"))
+    );
+    assert_eq!(
+        read_to_string(dir_path.join("original/file1.txt"))?,
+        read_to_string(dir_path.join("target/file1.txt"))?
+    );
+    Ok(())
+}
+
+/// Run git in the directory for test setup.
+fn git(dir: &Path, args: &[&str]) -> Result<()> {
+    let status = Command::new("git")
+        .current_dir(dir)
+        .args([
+            "-c",
+            "user.name=devobs",
+            "-c",
+            "user.email=devobs@example.com",
+            "-c",
+            "commit.gpgsign=false",
+        ])
+        .args(args)
+        .status()?;
+    assert!(status.success(), "git {args:?} failed");
+    Ok(())
+}
+
+/// Test comparing with `HEAD`, where the working tree is already dirty before running the
+/// command but the command restores it.
+#[test]
+fn test_against_head_restored() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+    });
+    let dir_path = temp_dir.path();
+    git(dir_path, &["init", "--quiet"])?;
+    git(dir_path, &["add", "."])?;
+    git(
+        dir_path,
+        &["commit", "--quiet", "--message", "Initial commit"],
+    )?;
+    write(dir_path.join("target/file1.txt"), "Unformatted content")?;
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .args(["--against", "HEAD"])
+        .arg("--")
+        .args(["git", "checkout", "--quiet", "--", "target/file1.txt"])
+        .assert();
+
+    // Assert
+    let result = assert.success().code(0);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(stderr, "");
+    Ok(())
+}
+
+/// Test comparing with `HEAD`, reporting files changed in the working tree including untracked
+/// ones. Files outside of the target or not matching the patterns are ignored.
+#[test]
+fn test_against_head_changes() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+        "target/subdir/file2.txt" => "Content of file 2",
+        "target/file3.log" => "Content of file 3",
+        "other/file4.txt" => "Content of file 4",
+    });
+    let dir_path = temp_dir.path();
+    git(dir_path, &["init", "--quiet"])?;
+    git(dir_path, &["add", "."])?;
+    git(
+        dir_path,
+        &["commit", "--quiet", "--message", "Initial commit"],
+    )?;
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .args(["--exclude", "**/*.log"])
+        .args(["--against", "HEAD"])
+        .arg("--")
+        .args([
+            "sh",
+            "-c",
+            "echo 'Modified' > target/file1.txt \
+             && rm target/subdir/file2.txt \
+             && echo 'New' > target/file5.txt \
+             && echo 'Modified' > target/file3.log \
+             && echo 'Modified' > other/file4.txt",
+        ])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stderr,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    Ok(())
+}
+
+/// Test comparing with `HEAD` when the command fails after changing files. The program should
+/// exit with the exit code of the command, after reporting changes.
+#[test]
+fn test_against_head_command_error() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+    });
+    let dir_path = temp_dir.path();
+    git(dir_path, &["init", "--quiet"])?;
+    git(dir_path, &["add", "."])?;
+    git(
+        dir_path,
+        &["commit", "--quiet", "--message", "Initial commit"],
+    )?;
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .args(["--against", "HEAD"])
+        .arg("--")
+        .args(["sh", "-c", "echo 'Modified' > target/file1.txt && exit 3"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(3);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stderr,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    Ok(())
+}
+
+/// Test comparing with the git index, where changes staged before running the command are not
+/// reported.
+#[test]
+fn test_against_git_index() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+    });
+    let dir_path = temp_dir.path();
+    git(dir_path, &["init", "--quiet"])?;
+    git(dir_path, &["add", "."])?;
+    git(
+        dir_path,
+        &["commit", "--quiet", "--message", "Initial commit"],
+    )?;
+    write(dir_path.join("target/file1.txt"), "Staged content")?;
+    git(dir_path, &["add", "."])?;
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .args(["--against", "git-index"])
+        .arg("--")
+        .args(["echo", "Hello, World!"])
+        .assert();
+
+    // Assert
+    assert.success().code(0);
+    Ok(())
+}
+
+/// Test running the command in a sandbox, which leaves the target untouched.
+#[test]
+fn test_sandbox() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+        "target/subdir/file2.txt" => "Content of file 2",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .arg("--sandbox")
+        .arg("--")
+        .args([
+            "sh",
+            "-c",
+            "echo 'Modified' > target/file1.txt && rm target/subdir/file2.txt",
+        ])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(
+        "sandbox_stdout",
+        normalize_console_output(
+            stdout,
+            hmap! {
+                to_str!(dir_path) => "<temp_dir>"
+            }
+        )
+    );
+    assert_snapshot!(
+        "sandbox_stderr",
+        normalize_console_output(
+            stderr,
+            hmap! {
+                to_str!(dir_path) => "<temp_dir>"
+            }
+        )
+    );
+    assert_eq!(
+        read_to_string(dir_path.join("target/file1.txt"))?,
+        "Content of file 1"
+    );
+    assert!(dir_path.join("target/subdir/file2.txt").exists());
+    Ok(())
+}
+
+/// Test running the command in a sandbox without `--sandbox-root`, where the current directory is
+/// outside of the target. The directory containing both should be copied, to run the command
+/// with files outside of the target, such as `Makefile`.
+#[test]
+fn test_sandbox_default_root() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "gen/api.txt" => "API\n",
+        "Makefile" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", "gen"])
+        .arg("--sandbox")
+        .arg("--")
+        .args([
+            "sh",
+            "-c",
+            "test -f Makefile && echo 'API v2' > gen/api.txt",
+        ])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert!(stderr.contains("modified: api.txt"));
+    assert_eq!(read_to_string(dir_path.join("gen/api.txt"))?, "API\n");
+    Ok(())
+}
+
+/// Test running the command in a sandbox of the project root, applying the changes back to the
+/// target. Changes outside of the target are not applied.
+#[test]
+fn test_sandbox_root_apply() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+        "target/subdir/file2.txt" => "Content of file 2",
+        "other/file3.txt" => "Content of file 3",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .arg("--sandbox")
+        .args(["--sandbox-root", to_str!(dir_path)])
+        .arg("--apply")
+        .arg("--")
+        .args([
+            "sh",
+            "-c",
+            "echo 'Modified' > target/file1.txt \
+             && rm target/subdir/file2.txt \
+             && cp other/file3.txt target/file3.txt \
+             && echo 'Modified' > other/file3.txt",
+        ])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stderr,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(
+        list_dir(&dir_path.join("target")),
+        vec!["file1.txt", "file3.txt"]
+    );
+    assert_eq!(
+        read_to_string(dir_path.join("target/file1.txt"))?,
+        "Modified\n"
+    );
+    assert_eq!(
+        read_to_string(dir_path.join("other/file3.txt"))?,
+        "Content of file 3"
+    );
+    Ok(())
+}
+
+/// Test restoring the target when changes are detected. Files not matching the patterns are left
+/// as changed by the command.
+#[test]
+fn test_restore_on_change() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+        "target/subdir/file2.txt" => "Content of file 2",
+        "target/file3.log" => "Content of file 3",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .args(["--exclude", "**/*.log"])
+        .arg("--restore-on-change")
+        .arg("--")
+        .args([
+            "sh",
+            "-c",
+            "echo 'Modified' > target/file1.txt \
+             && rm target/subdir/file2.txt \
+             && echo 'New' > target/file4.txt \
+             && echo 'Modified' > target/file3.log",
+        ])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(
+        "restore_on_change_stdout",
+        normalize_console_output(
+            stdout,
+            hmap! {
+                to_str!(dir_path) => "<temp_dir>"
+            }
+        )
+    );
+    assert_snapshot!(
+        "restore_on_change_stderr",
+        normalize_console_output(
+            stderr,
+            hmap! {
+                to_str!(dir_path) => "<temp_dir>"
+            }
+        )
+    );
+    assert_eq!(
+        list_dir(&dir_path.join("target")),
+        vec!["file1.txt", "file3.log", "subdir/file2.txt"]
+    );
+    assert_eq!(
+        read_to_string(dir_path.join("target/file1.txt"))?,
+        "Content of file 1"
+    );
+    assert_eq!(
+        read_to_string(dir_path.join("target/subdir/file2.txt"))?,
+        "Content of file 2"
+    );
+    assert_eq!(
+        read_to_string(dir_path.join("target/file3.log"))?,
+        "Modified\n"
+    );
+    Ok(())
+}
+
+/// Test restoring the target when the command fails or times out after changing files. The
+/// program should exit with the exit code of the command, after reporting changes.
+#[rstest]
+#[case("command_error", &[], "exit 3", 3)]
+#[case("timeout", &["--timeout", "300ms"], "sleep 10", 124)]
+fn test_restore_on_command_error(
+    #[case] name: &str,
+    #[case] options: &[&str],
+    #[case] then: &str,
+    #[case] code: i32,
+) -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .args(options)
+        .arg("--restore-on-change")
+        .arg("--")
+        .args([
+            "sh",
+            "-c",
+            &format!(
+                "echo 'Modified' > target/file1.txt && echo 'New' > target/file2.txt && {then}"
+            ),
+        ])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(code);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_snapshot!(
+        format!("restore_on_command_error_{name}"),
+        normalize_console_output(
+            stderr,
+            hmap! {
+                to_str!(dir_path) => "<temp_dir>"
+            }
+        )
+    );
+    assert_eq!(list_dir(&dir_path.join("target")), vec!["file1.txt"]);
+    assert_eq!(
+        read_to_string(dir_path.join("target/file1.txt"))?,
+        "Content of file 1"
+    );
+    Ok(())
+}
+
+/// Test tracking file modes, detecting a change of the executable bit.
+#[cfg(unix)]
+#[test]
+fn test_track_mode() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/script.sh" => "echo 'Hello, World!'",
+    });
+    let dir_path = temp_dir.path();
+    let status = Command::new("chmod")
+        .args(["644", to_str!(dir_path.join("target/script.sh"))])
+        .status()?;
+    assert!(status.success());
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .args(["--track", "mode"])
+        .arg("--")
+        .args(["chmod", "755", "target/script.sh"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stderr,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    Ok(())
+}
+
+/// Test tracking symbolic links, detecting files replaced with links and changed link targets.
+#[cfg(unix)]
+#[test]
+fn test_track_symlinks() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content",
+        "target/file2.txt" => "Content",
+        "target/file3.txt" => "Content",
+    });
+    let dir_path = temp_dir.path();
+    std::os::unix::fs::symlink("file1.txt", dir_path.join("target/link.txt"))?;
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .args(["--track", "symlinks,mode"])
+        .arg("--")
+        .args([
+            "sh",
+            "-c",
+            "ln -sf file2.txt target/link.txt \
+             && rm target/file3.txt \
+             && ln -s file1.txt target/file3.txt",
+        ])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stderr,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    Ok(())
+}
+
+/// Test hashing with multiple threads, which gives the same result as a single thread.
+#[test]
+fn test_jobs() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+        "target/file2.txt" => "Content of file 2",
+        "target/subdir/file3.txt" => "Content of file 3",
+        "target/subdir/file4.txt" => "Content of file 4",
+    });
+    let dir_path = temp_dir.path();
+    let run = |jobs: &str| {
+        let mut cmd = get_cmd();
+        let assert = cmd
+            .arg("assert-diff")
+            .args(["--target", to_str!(dir_path.join("target"))])
+            .args(["--jobs", jobs])
+            .arg("--")
+            .args(["true"])
+            .assert();
+        let (stdout, _) = parse_output(assert.success().get_output());
+        stdout
+    };
+
+    // Act
+    let sequential = run("1");
+    let parallel = run("4");
+
+    // Assert
+    assert_eq!(sequential, parallel);
+    assert_snapshot!(sequential);
+    Ok(())
+}
+
+/// Test killing a command which does not exit within the timeout. It should exit with code 124,
+/// distinct from a change or an error of the command.
+#[test]
+fn test_timeout() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .args(["--timeout", "200ms"])
+        .arg("--")
+        .args(["sleep", "10"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(124);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stderr,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    Ok(())
+}
+
+/// Test killing descendants of the command on timeout, such as commands run by the shell. None of
+/// them should survive to change files after the program exits.
+#[cfg(unix)]
+#[test]
+fn test_timeout_kills_descendants() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .args(["--timeout", "200ms"])
+        .arg("--shell")
+        .arg("--")
+        .args(["(sleep 1 && echo 'Survived' > marker.txt); exit 0"])
+        .assert();
+    sleep(Duration::from_millis(1500));
+
+    // Assert
+    assert.failure().code(124);
+    assert!(!dir_path.join("marker.txt").exists());
+    Ok(())
+}
+
+/// Test interrupting the program without `--timeout`, as Ctrl-C in the terminal does by signaling
+/// the foreground process group. The command should be interrupted along with the program.
+#[cfg(unix)]
+#[test]
+fn test_interrupt_kills_command() -> Result<()> {
+    use std::os::unix::process::CommandExt;
+
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+    });
+    let dir_path = temp_dir.path();
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin(env!("CARGO_PKG_NAME")))
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .arg("--shell")
+        .arg("--")
+        .arg("sleep 1 && echo 'Survived' > marker.txt")
+        .process_group(0)
+        .spawn()?;
+    sleep(Duration::from_millis(300));
+
+    // Act
+    let status = Command::new("kill")
+        .args(["-s", "INT", "--", &format!("-{}", child.id())])
+        .status()?;
+    child.wait()?;
+    sleep(Duration::from_millis(1500));
+
+    // Assert
+    assert!(status.success());
+    assert!(!dir_path.join("marker.txt").exists());
+    Ok(())
+}
+
+/// Test running the command with the shell and environment variables.
+#[test]
+fn test_shell_env() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .args(["--env", "GREETING=Hello"])
+        .args(["--env", "NAME=World"])
+        .arg("--shell")
+        .arg("--")
+        .args(["echo", "\"$GREETING, $NAME\"", ">", "target/file2.txt"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stderr,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(
+        read_to_string(dir_path.join("target/file2.txt"))?,
+        "Hello, World\n"
+    );
+    Ok(())
+}
+
+/// Test running the command in another working directory, also mapped into the sandbox.
+#[rstest]
+#[case::in_place(false)]
+#[case::sandbox(true)]
+fn test_cwd(#[case] sandbox: bool) -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/subdir/file1.txt" => "Content of file 1",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    cmd.current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .args(["--cwd", "target/subdir"]);
+    if sandbox {
+        cmd.arg("--sandbox");
+    }
+    let assert = cmd
+        .arg("--")
+        .args(["sh", "-c", "echo 'Modified' > file1.txt"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert!(stderr.contains("modified: subdir/file1.txt"));
+    assert_eq!(
+        read_to_string(dir_path.join("target/subdir/file1.txt"))?,
+        if sandbox {
+            "Content of file 1"
+        } else {
+            "Modified\n"
+        }
+    );
+    Ok(())
+}
+
+/// Test running the command in a working directory outside the given sandbox root, which is an
+/// error.
+#[test]
+fn test_cwd_outside_sandbox() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+        "other/" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", to_str!(dir_path.join("target"))])
+        .args(["--cwd", "other"])
+        .arg("--sandbox")
+        .args(["--sandbox-root", "target"])
+        .arg("--")
+        .args(["true"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stderr,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    Ok(())
+}
+
+/// Test watching several targets with their own patterns. Changes are reported per target, and
+/// targets without changes are left out.
+#[test]
+fn test_multiple_targets() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "proto/gen/api.pb.go" => "package api",
+        "proto/gen/build.log" => "Build log",
+        "docs/api/index.md" => "# API",
+        "web/src/client/index.ts" => "export {};",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", "proto/gen;exclude=**/*.log"])
+        .args(["--target", "docs/api;include=**/*.md"])
+        .args(["--target", "web/src/client"])
+        .arg("--")
+        .args([
+            "sh",
+            "-c",
+            "echo 'package api // changed' > proto/gen/api.pb.go \
+             && echo 'Another build log' > proto/gen/build.log \
+             && echo 'Not documentation' > docs/api/notes.txt \
+             && echo '# Models' > docs/api/models.md",
+        ])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(
+        "multiple_targets_stdout",
+        normalize_console_output(
+            stdout,
+            hmap! {
+                to_str!(dir_path) => "<temp_dir>"
+            }
+        )
+    );
+    assert_snapshot!(
+        "multiple_targets_stderr",
+        normalize_console_output(
+            stderr,
+            hmap! {
+                to_str!(dir_path) => "<temp_dir>"
+            }
+        )
+    );
+    Ok(())
+}
+
+/// Test watching several targets in a sandbox, copying their common parent directory. The patch
+/// has paths prefixed with the targets, to be applied in the current directory.
+#[test]
+fn test_multiple_targets_sandbox_patch() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "proto/gen/api.txt" => "API\n",
+        "docs/api/index.md" => "# API\n",
+        "Makefile" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", "proto/gen"])
+        .args(["--target", "docs/api"])
+        .arg("--sandbox")
+        .args(["--patch-out", to_str!(dir_path.join("changes.patch"))])
+        .arg("--")
+        .args([
+            "sh",
+            "-c",
+            "test -f Makefile \
+             && echo 'API v2' > proto/gen/api.txt \
+             && echo '# API v2' > docs/api/index.md",
+        ])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_snapshot!(
+        "multiple_targets_sandbox_patch_stderr",
+        normalize_console_output(
+            stderr,
+            hmap! {
+                to_str!(dir_path) => "<temp_dir>"
+            }
+        )
+    );
+    assert_eq!(read_to_string(dir_path.join("proto/gen/api.txt"))?, "API\n");
+    assert_snapshot!(
+        "multiple_targets_sandbox_patch",
+        read_to_string(dir_path.join("changes.patch"))?
+    );
+    let status = Command::new("git")
+        .current_dir(dir_path)
+        .args(["apply", "changes.patch"])
+        .status()?;
+    assert!(status.success());
+    assert_eq!(
+        read_to_string(dir_path.join("proto/gen/api.txt"))?,
+        "API v2\n"
+    );
+    assert_eq!(
+        read_to_string(dir_path.join("docs/api/index.md"))?,
+        "# API v2\n"
+    );
+    Ok(())
+}
+
+/// Test an invalid target spec, which is rejected before running the command.
+#[test]
+fn test_invalid_target_spec() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", "target;filter=**/*.txt"])
+        .arg("--")
+        .args(["true"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(2);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_snapshot!(stderr);
+    Ok(())
+}
+
+/// Test allowing changes to some files. Allowed changes are reported but do not fail, while other
+/// changes still do.
+#[rstest]
+#[case::only_allowed("allow_changed_only_allowed", "echo '1.0.1' > target/CHANGELOG.md", 0)]
+#[case::unexpected(
+    "allow_changed_unexpected",
+    "echo '1.0.1' > target/CHANGELOG.md && echo 'Modified' > target/file1.txt",
+    1
+)]
+fn test_allow_changed(#[case] name: &str, #[case] script: &str, #[case] code: i32) -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/CHANGELOG.md" => "1.0.0",
+        "target/file1.txt" => "Content of file 1",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", "target"])
+        .args(["--allow-changed", "CHANGELOG.md"])
+        .arg("--")
+        .args(["sh", "-c", script])
+        .assert();
+
+    // Assert
+    let result = assert.code(code);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(
+        name,
+        format!(
+            "{}\n---\n{}",
+            normalize_console_output(
+                stdout,
+                hmap! {
+                    to_str!(dir_path) => "<temp_dir>"
+                }
+            ),
+            stderr
+        )
+    );
+    Ok(())
+}
+
+/// Test allowing changes with `--restore-on-change`. Allowed changes should be kept if the check
+/// passes, while every change is restored if it fails.
+#[rstest]
+#[case::allowed(
+    "echo '1.0.1' > target/CHANGELOG.md",
+    0,
+    "1.0.1\n",
+    "Content of file 1"
+)]
+#[case::unexpected(
+    "echo '1.0.1' > target/CHANGELOG.md && echo 'Modified' > target/file1.txt",
+    1,
+    "1.0.0",
+    "Content of file 1"
+)]
+fn test_allow_changed_restore_on_change(
+    #[case] script: &str,
+    #[case] code: i32,
+    #[case] changelog: &str,
+    #[case] file1: &str,
+) -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/CHANGELOG.md" => "1.0.0",
+        "target/file1.txt" => "Content of file 1",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", "target"])
+        .args(["--allow-changed", "CHANGELOG.md"])
+        .arg("--restore-on-change")
+        .arg("--")
+        .args(["sh", "-c", script])
+        .assert();
+
+    // Assert
+    assert.code(code);
+    assert_eq!(
+        read_to_string(dir_path.join("target/CHANGELOG.md"))?,
+        changelog
+    );
+    assert_eq!(read_to_string(dir_path.join("target/file1.txt"))?, file1);
+    Ok(())
+}
+
+/// Test expecting changes to some files. It should fail if no matching file has changed.
+#[rstest]
+#[case::created(
+    "expect_changed_created",
+    "touch target/migrations/0002_add_users.sql",
+    0
+)]
+#[case::missing("expect_changed_missing", "true", 1)]
+#[case::missing_with_unexpected(
+    "expect_changed_missing_with_unexpected",
+    "echo 'Modified' > target/file1.txt",
+    1
+)]
+fn test_expect_changed(#[case] name: &str, #[case] script: &str, #[case] code: i32) -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/migrations/0001_initial.sql" => "CREATE TABLE items ();",
+        "target/file1.txt" => "Content of file 1",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", "target"])
+        .args(["--expect-changed", "migrations/*.sql"])
+        .arg("--")
+        .args(["sh", "-c", script])
+        .assert();
+
+    // Assert
+    let result = assert.code(code);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(
+        name,
+        format!(
+            "{}\n---\n{}",
+            normalize_console_output(
+                stdout,
+                hmap! {
+                    to_str!(dir_path) => "<temp_dir>"
+                }
+            ),
+            stderr
+        )
+    );
+    Ok(())
+}
+
+/// Test normalizing contents of files before hashing. Differences which disappear after
+/// normalization are reported but do not fail, while other differences still do.
+#[rstest]
+#[case::normalized(
+    "normalize_normalized",
+    "printf '// Generated at 2026-10-17T00:00:00Z\\nexport {};\\n' > target/client.ts",
+    0
+)]
+#[case::changed(
+    "normalize_changed",
+    "printf '// Generated at 2026-10-17T00:00:00Z\\nexport { Client };\\n' > target/client.ts",
+    1
+)]
+fn test_normalize(#[case] name: &str, #[case] script: &str, #[case] code: i32) -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/client.ts" => "// Generated at 2026-10-16T00:00:00Z\r\nexport {};  \r\n",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", "target"])
+        .args(["--normalize-eol", "**/*.ts"])
+        .args(["--strip-trailing-whitespace", "**/*.ts"])
+        .args(["--ignore-line-regex", "**/*.ts=^// Generated at "])
+        .arg("--")
+        .args(["sh", "-c", script])
+        .assert();
+
+    // Assert
+    let result = assert.code(code);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(
+        name,
+        format!(
+            "{}\n---\n{}",
+            normalize_console_output(
+                stdout,
+                hmap! {
+                    to_str!(dir_path) => "<temp_dir>"
+                }
+            ),
+            stderr
+        )
+    );
+    Ok(())
+}
+
+/// Test comparing data files by their parsed values. Reordered keys and reformatting do not fail,
+/// while changed values are reported with paths of their keys.
+#[rstest]
+#[case::reformatted(
+    "semantic_reformatted",
+    r#"printf '{"version": "1.0.0", "name": "app"}' > target/package.json \
+     && printf 'info:\n  title: API\nopenapi: 3.1.0\n' > target/openapi.yaml"#,
+    0
+)]
+#[case::changed(
+    "semantic_changed",
+    r#"printf '{"name": "app", "version": "1.0.1", "private": true}' > target/package.json \
+     && printf 'openapi: 3.1.0\ninfo: {}\n' > target/openapi.yaml"#,
+    1
+)]
+fn test_semantic(#[case] name: &str, #[case] script: &str, #[case] code: i32) -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/package.json" => "{\n  \"name\": \"app\",\n  \"version\": \"1.0.0\"\n}\n",
+        "target/openapi.yaml" => "openapi: 3.1.0\ninfo:\n  title: API\n",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("assert-diff")
+        .args(["--target", "target"])
+        .args(["--semantic", "**/*.json,**/*.yaml"])
+        .arg("--")
+        .args(["sh", "-c", script])
+        .assert();
+
+    // Assert
+    let result = assert.code(code);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(
+        name,
+        format!(
+            "{}\n---\n{}",
+            normalize_console_output(
+                stdout,
+                hmap! {
+                    to_str!(dir_path) => "<temp_dir>"
+                }
+            ),
+            stderr
+        )
+    );
+    Ok(())
+}
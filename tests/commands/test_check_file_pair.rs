@@ -295,3 +295,1014 @@ fn test_create_if_not_exists_dry_run() -> Result<()> {
     );
     Ok(())
 }
+
+/// Test for `--check-orphans` option. Files in the `to` directory without a source
+/// should be reported separately from missing pairs.
+#[test]
+fn test_check_orphans() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/main.py" => "",
+        "src/utils/slack/template.py" => "",
+        "tests/conftest.py" => "",
+        "tests/test_main.py" => "",
+        "tests/utils/test_logger.py" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("src"))])
+        .args(["--to", to_str!(dir_path.join("tests"))])
+        .args(["--include", "**/*.py"])
+        .args(["--expect", "{to}/{relative_from}/test_{filename}"])
+        .args(["--check-orphans"])
+        .args(["--to-include", "**/test_*.py"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(
+        first_line(stderr),
+        "Error: There are 1 missing files. Use `--create-if-not-exists` to create them. There are 1 orphaned files. Use `--delete-orphans` to delete them."
+    );
+    Ok(())
+}
+
+/// Test for `--delete-orphans` option.
+#[test]
+fn test_delete_orphans() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/main.py" => "",
+        "tests/conftest.py" => "",
+        "tests/test_main.py" => "",
+        "tests/utils/test_logger.py" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("src"))])
+        .args(["--to", to_str!(dir_path.join("tests"))])
+        .args(["--include", "**/*.py"])
+        .args(["--expect", "{to}/{relative_from}/test_{filename}"])
+        .args(["--check-orphans"])
+        .args(["--to-exclude", "conftest.py"])
+        .args(["--delete-orphans"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(first_line(stderr), "Error: Deleted 1 orphaned files.");
+    assert_eq!(
+        list_dir(dir_path),
+        &["src/main.py", "tests/conftest.py", "tests/test_main.py"]
+    );
+    Ok(())
+}
+
+/// Test for `--delete-orphans` with pairs next to their sources, where `from` and `to` are the
+/// same directory. Source files should never be reported as orphans nor deleted.
+#[test]
+fn test_delete_orphans_colocated() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/Button.tsx" => "",
+        "src/Button.test.tsx" => "",
+        "src/Card.tsx" => "",
+        "src/Card.test.tsx" => "",
+        "src/Legacy.test.tsx" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("src"))])
+        .args(["--to", to_str!(dir_path.join("src"))])
+        .args(["--include", "**/*.tsx"])
+        .args(["--exclude", "**/*.test.tsx"])
+        .args(["--expect", "{to}/{relative_from}/{stem}.test.tsx"])
+        .args(["--check-orphans"])
+        .args(["--delete-orphans"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_eq!(first_line(stderr), "Error: Deleted 1 orphaned files.");
+    assert_eq!(
+        list_dir(dir_path),
+        &[
+            "src/Button.test.tsx",
+            "src/Button.tsx",
+            "src/Card.test.tsx",
+            "src/Card.tsx",
+        ]
+    );
+    Ok(())
+}
+
+/// Test for `--delete-orphans` with `--dry-run` option. Orphaned files should be kept.
+#[test]
+fn test_delete_orphans_dry_run() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/main.py" => "",
+        "tests/test_main.py" => "",
+        "tests/utils/test_logger.py" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("--dry-run")
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("src"))])
+        .args(["--to", to_str!(dir_path.join("tests"))])
+        .args(["--include", "**/*.py"])
+        .args(["--expect", "{to}/{relative_from}/test_{filename}"])
+        .args(["--check-orphans"])
+        .args(["--delete-orphans"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(first_line(stderr), "Error: Deleted 1 orphaned files.");
+    assert_eq!(
+        list_dir(dir_path),
+        &[
+            "src/main.py",
+            "tests/test_main.py",
+            "tests/utils/test_logger.py"
+        ]
+    );
+    Ok(())
+}
+
+/// Test for `--create-template` option. Created files should be rendered from the template.
+#[test]
+fn test_create_template() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/utils/logger.py" => "",
+        "templates/test.py.tmpl" => "from {relative_from}.{stem} import *\n\n\ndef test_{stem}() -> None:\n    assert {{}} == {{}}\n",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("src"))])
+        .args(["--to", to_str!(dir_path.join("tests"))])
+        .args(["--include", "**/*.py"])
+        .args(["--expect", "{to}/{relative_from}/test_{filename}"])
+        .args(["--create-if-not-exists"])
+        .args([
+            "--create-template",
+            to_str!(dir_path.join("templates/test.py.tmpl")),
+        ])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_eq!(first_line(stderr), "Error: Created 1 missing files.");
+    assert_snapshot!(std::fs::read_to_string(
+        dir_path.join("tests/utils/test_logger.py")
+    )?);
+    Ok(())
+}
+
+/// Test for `--create-template-content` option, which provides the template inline.
+#[test]
+fn test_create_template_content() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/main.py" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("src"))])
+        .args(["--to", to_str!(dir_path.join("tests"))])
+        .args(["--include", "**/*.py"])
+        .args(["--expect", "{to}/{relative_from}/test_{filename}"])
+        .args(["--create-if-not-exists"])
+        .args(["--create-template-content", "# Tests for {filename}\n"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_eq!(first_line(stderr), "Error: Created 1 missing files.");
+    assert_eq!(
+        std::fs::read_to_string(dir_path.join("tests/test_main.py"))?,
+        "# Tests for main.py\n"
+    );
+    Ok(())
+}
+
+/// Test that the template is not rendered without `--create-if-not-exists`, so variables it uses
+/// may be missing when files are only checked.
+#[test]
+fn test_create_template_check_only() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/main.py" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("src"))])
+        .args(["--to", to_str!(dir_path.join("tests"))])
+        .args(["--include", "**/*.py"])
+        .args(["--expect", "{to}/{relative_from}/test_{filename}"])
+        .args(["--create-template-content", "# Tests for {module}\n"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_eq!(
+        first_line(stderr),
+        "Error: There are 1 missing files. Use `--create-if-not-exists` to create them."
+    );
+    assert_eq!(list_dir(dir_path), &["src/main.py"]);
+    Ok(())
+}
+
+/// Test for multiple `--expect` options. A source file is paired if any of the expected files
+/// exists, and missing files are created at the first non-glob expected pattern.
+#[test]
+fn test_multiple_expect() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/main.py" => "",
+        "src/utils/logger.py" => "",
+        "src/utils/slack/template.py" => "",
+        "tests/integration/test_main.py" => "",
+        "tests/e2e/utils/test_logger_stdout.py" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("src"))])
+        .args(["--to", to_str!(dir_path.join("tests"))])
+        .args(["--include", "**/*.py"])
+        .args(["--expect", "{to}/**/test_{stem}_*.py"])
+        .args(["--expect", "{to}/unit/{relative_from}/test_{filename}"])
+        .args([
+            "--expect",
+            "{to}/integration/{relative_from}/test_{filename}",
+        ])
+        .args(["--create-if-not-exists"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(first_line(stderr), "Error: Created 1 missing files.");
+    assert_eq!(
+        list_dir(dir_path),
+        &[
+            "src/main.py",
+            "src/utils/logger.py",
+            "src/utils/slack/template.py",
+            "tests/e2e/utils/test_logger_stdout.py",
+            "tests/integration/test_main.py",
+            "tests/unit/utils/slack/test_template.py",
+        ]
+    );
+    Ok(())
+}
+
+/// Test that missing files cannot be created if all expected patterns are globs.
+#[test]
+fn test_glob_expect_cannot_create() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/main.py" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("src"))])
+        .args(["--to", to_str!(dir_path.join("tests"))])
+        .args(["--include", "**/*.py"])
+        .args(["--expect", "{to}/**/test_{stem}*.py"])
+        .args(["--create-if-not-exists"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(
+        first_line(stderr),
+        "Error: There are 1 missing files which cannot be created."
+    );
+    assert_eq!(list_dir(dir_path), &["src/main.py"]);
+    Ok(())
+}
+
+/// Test for file names containing glob characters, such as `pages/[id].tsx`. Substituted values
+/// should be matched literally, whether the expected pattern is a glob or not.
+#[test]
+fn test_bracketed_file_names() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/pages/[id].tsx" => "",
+        "src/pages/[slug]/index.tsx" => "",
+        "tests/e2e/[id].test.tsx" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("src"))])
+        .args(["--to", to_str!(dir_path.join("tests"))])
+        .args(["--include", "**/*.tsx"])
+        .args(["--expect", "{to}/**/{stem}.test.tsx"])
+        .args(["--expect", "{to}/{relative_from}/{stem}.test.tsx"])
+        .args(["--create-if-not-exists"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(first_line(stderr), "Error: Created 1 missing files.");
+    assert_eq!(
+        list_dir(dir_path),
+        &[
+            "src/pages/[id].tsx",
+            "src/pages/[slug]/index.tsx",
+            "tests/e2e/[id].test.tsx",
+            "tests/pages/[slug]/index.test.tsx",
+        ]
+    );
+    Ok(())
+}
+
+/// Test for `--path-regex` option, where captures come from the directory structure.
+/// Files not matching the regex are skipped with `--on-regex-mismatch skip`.
+#[test]
+fn test_path_regex() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/lib.rs" => "",
+        "src/users/handlers/create.rs" => "",
+        "src/users/handlers/delete.rs" => "",
+        "src/posts/handlers/list.rs" => "",
+        "tests/users/create_test.rs" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("src"))])
+        .args(["--to", to_str!(dir_path.join("tests"))])
+        .args(["--include", "**/*.rs"])
+        .args([
+            "--path-regex",
+            r"^(?P<domain>[^/]+)/handlers/(?P<name>.+)\.rs$",
+        ])
+        .args(["--expect", "{to}/{domain}/{name}_test.rs"])
+        .args(["--on-regex-mismatch", "skip"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(
+        first_line(stderr),
+        "Error: There are 2 missing files. Use `--create-if-not-exists` to create them."
+    );
+    Ok(())
+}
+
+/// Test for `--on-regex-mismatch error` option, which stops on the first mismatching file.
+#[test]
+fn test_on_regex_mismatch_error() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "tests/conftest.py" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("tests"))])
+        .args(["--to", to_str!(dir_path.join("src"))])
+        .args(["--include", "**/*.py"])
+        .args(["--filename-regex", "^test_(?P<filename>.*)$"])
+        .args(["--on-regex-mismatch", "error"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_eq!(stdout, "");
+    assert_eq!(
+        normalize_console_output(
+            first_line(stderr),
+            hmap! {
+                to_str!(dir_path) => "<temp_dir>"
+            }
+        ),
+        "Error: Filename regex did not match for file <temp_dir>/tests/conftest.py: conftest.py"
+    );
+    Ok(())
+}
+
+/// Test for filters in the `--expect` template, applied to both default and captured variables.
+#[test]
+fn test_expect_filters() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/components/UserProfile.tsx" => "",
+        "src/components/NavBar.tsx" => "",
+        "src/pages/HomePage.tsx" => "",
+        "tests/components/user-profile.test.ts" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("src"))])
+        .args(["--to", to_str!(dir_path.join("tests"))])
+        .args(["--include", "**/*.tsx"])
+        .args(["--path-regex", r"^(?P<kind>[^/]+)/"])
+        .args(["--expect", "{to}/{kind|lower}/{stem|kebab}.test.ts"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(
+        first_line(stderr),
+        "Error: There are 2 missing files. Use `--create-if-not-exists` to create them."
+    );
+    Ok(())
+}
+
+/// Test that likely renamed or misplaced files are suggested for missing pairs.
+#[test]
+fn test_suggest_misplaced_files() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/main.py" => "",
+        "src/utils/logger.py" => "",
+        "src/utils/slack/template.py" => "",
+        "tests/utils/test_loger.py" => "",
+        "tests/test_logger.py" => "",
+        "tests/test_template.py" => "",
+        "tests/test_main.py" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("src"))])
+        .args(["--to", to_str!(dir_path.join("tests"))])
+        .args(["--include", "**/*.py"])
+        .args(["--expect", "{to}/{relative_from}/test_{filename}"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(
+        first_line(stderr),
+        "Error: There are 2 missing files. Use `--create-if-not-exists` to create them."
+    );
+    Ok(())
+}
+
+/// Test for `--fix-moves` option. Misplaced files with a single suggestion should be moved.
+#[test]
+fn test_fix_moves() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/main.py" => "",
+        "src/utils/logger.py" => "",
+        "src/utils/slack/template.py" => "",
+        "tests/utils/test_loger.py" => "",
+        "tests/test_logger.py" => "",
+        "tests/test_template.py" => "",
+        "tests/test_main.py" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("src"))])
+        .args(["--to", to_str!(dir_path.join("tests"))])
+        .args(["--include", "**/*.py"])
+        .args(["--expect", "{to}/{relative_from}/test_{filename}"])
+        .args(["--fix-moves"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(
+        first_line(stderr),
+        "Error: Moved 1 misplaced files. There are 1 missing files. Use `--create-if-not-exists` to create them."
+    );
+    assert_eq!(
+        list_dir(dir_path),
+        &[
+            "src/main.py",
+            "src/utils/logger.py",
+            "src/utils/slack/template.py",
+            "tests/test_logger.py",
+            "tests/test_main.py",
+            "tests/utils/slack/test_template.py",
+            "tests/utils/test_loger.py",
+        ]
+    );
+    Ok(())
+}
+
+/// Test for content predicates. Source files not meeting them do not require a pair,
+/// and the reason is reported in debug logs.
+#[test]
+fn test_content_predicates() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/__init__.py" => "\n\n",
+        "src/constants.py" => "VERSION = '1.0.0'\n",
+        "src/types.py" => "# type: ignore\ndef foo() -> None: ...\n",
+        "src/main.py" => "def main() -> None:\n    pass\n",
+        "src/utils/logger.py" => "class Logger:\n    def log(self) -> None:\n        pass\n",
+        "src/utils/data.py" => "DATA = '0123456789abcdef'\ndef load() -> str:\n    return DATA\n",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .args(["--log-level", "debug"])
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("src"))])
+        .args(["--to", to_str!(dir_path.join("tests"))])
+        .args(["--include", "**/*.py"])
+        .args(["--expect", "{to}/{relative_from}/test_{filename}"])
+        .args(["--min-lines", "1"])
+        .args(["--require-content", "def "])
+        .args(["--forbid-content", "# type: ignore"])
+        .args(["--max-size", "60"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    let stdout = stdout
+        .lines()
+        .filter(|l| l.contains("Skipping file") || l.starts_with("[WARN]"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(
+        first_line(stderr),
+        "Error: There are 2 missing files. Use `--create-if-not-exists` to create them."
+    );
+    Ok(())
+}
+
+/// Test running all rules declared in `devobs.toml`, reporting the result per rule.
+#[test]
+fn test_config_rules() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "devobs.toml" => r#"
+[check-file-pair.python]
+from = "src"
+to = "tests"
+include = ["**/*.py"]
+expect = ["{to}/{relative_from}/test_{filename}"]
+
+[check-file-pair.docs]
+from = "src"
+to = "docs"
+include = ["**/*.py"]
+exclude = ["**/_*.py"]
+expect = ["{to}/{relative_from}/{stem}.md"]
+"#,
+        "src/main.py" => "",
+        "src/_internal.py" => "",
+        "tests/test_main.py" => "",
+        "docs/main.md" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd.current_dir(dir_path).arg("check-file-pair").assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_snapshot!(normalize_console_output(
+        stderr,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    Ok(())
+}
+
+/// Test running a subset of rules declared in `[tool.devobs]` section of `pyproject.toml`
+/// with `--rule` option.
+#[test]
+fn test_config_rules_pyproject_select() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "pyproject.toml" => r#"
+[project]
+name = "example"
+
+[tool.devobs.check-file-pair.python]
+from = "src"
+to = "tests"
+include = ["**/*.py"]
+expect = ["{to}/{relative_from}/test_{filename}"]
+
+[tool.devobs.check-file-pair.docs]
+from = "src"
+to = "docs"
+include = ["**/*.py"]
+expect = ["{to}/{relative_from}/{stem}.md"]
+"#,
+        "src/main.py" => "",
+        "tests/test_main.py" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("check-file-pair")
+        .args(["--rule", "python"])
+        .assert();
+
+    // Assert
+    let result = assert.success().code(0);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(stderr, "");
+    Ok(())
+}
+
+/// Test that an unknown rule name is reported with the available rules.
+#[test]
+fn test_config_unknown_rule() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "config/devobs.toml" => r#"
+[check-file-pair.python]
+from = "src"
+to = "tests"
+"#,
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--config", to_str!(dir_path.join("config/devobs.toml"))])
+        .args(["--rule", "rust"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_eq!(stdout, "");
+    assert_eq!(
+        first_line(stderr),
+        "Error: Unknown rule `rust`, available rules: python"
+    );
+    Ok(())
+}
+
+/// Test that rule options cannot be given when running rules from the configuration file, as
+/// they would be ignored.
+#[test]
+fn test_config_rule_options() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "devobs.toml" => r#"
+[check-file-pair.rust]
+from = "src"
+to = "tests"
+"#,
+        "src/main.rs" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("check-file-pair")
+        .args(["--include", "**/*.rs"])
+        .arg("--create-if-not-exists")
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_eq!(stdout, "");
+    assert_eq!(
+        first_line(stderr),
+        "Error: Rule options cannot be given when running rules from the configuration file: \
+         --include, --create-if-not-exists. Specify `--from` and `--to` to run a rule given as \
+         command-line options."
+    );
+    assert_eq!(list_dir(dir_path), &["devobs.toml", "src/main.rs"]);
+    Ok(())
+}
+
+/// Test that a rule declared in the configuration file cannot delete orphaned files without
+/// checking for them.
+#[test]
+fn test_config_delete_orphans_requires_check_orphans() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "devobs.toml" => r#"
+[check-file-pair.python]
+from = "src"
+to = "tests"
+delete-orphans = true
+"#,
+        "tests/test_orphan.py" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd.current_dir(dir_path).arg("check-file-pair").assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(
+        first_line(stderr),
+        "[ERROR] Rule `python` failed: Deleting orphaned files requires `check-orphans` to be \
+         enabled."
+    );
+    assert_eq!(list_dir(dir_path), &["devobs.toml", "tests/test_orphan.py"]);
+    Ok(())
+}
+
+/// Test for `--format json` option. Results are printed as a JSON document to standard output,
+/// while logs are written to standard error.
+#[test]
+fn test_format_json() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/__init__.py" => "",
+        "src/main.py" => "def main() -> None: ...\n",
+        "src/utils/logger.py" => "def log() -> None: ...\n",
+        "tests/test_main.py" => "",
+        "tests/test_orphan.py" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .arg("check-file-pair")
+        .args(["--from", "src"])
+        .args(["--to", "tests"])
+        .args(["--include", "**/*.py"])
+        .args(["--expect", "{to}/{relative_from}/test_{filename}"])
+        .args(["--min-lines", "1"])
+        .args(["--check-orphans"])
+        .args(["--format", "json"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_snapshot!(normalize_console_output(
+        stderr,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    Ok(())
+}
+
+/// Test for files without extensions and with compound extensions, using `{base}`,
+/// `{extensions}` and `{parent}` variables.
+#[test]
+fn test_extensionless_and_compound_extensions() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "src/Dockerfile" => "",
+        "src/scripts/deploy" => "",
+        "src/web/types.d.ts" => "",
+        "src/web/button.test.ts" => "",
+        "docs/src/Dockerfile.md" => "",
+        "docs/scripts/deploy.md" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("src"))])
+        .args(["--to", to_str!(dir_path.join("docs"))])
+        .args(["--include", "**/*"])
+        .args(["--exclude", "*/"])
+        .args(["--expect", "{to}/{parent}/{base}.md"])
+        .args(["--expect", "{to}/{parent}/{extensions}/{base}.md"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(
+        first_line(stderr),
+        "Error: There are 2 missing files. Use `--create-if-not-exists` to create them."
+    );
+    Ok(())
+}
+
+/// Test `--fix-moves` with pairs next to their sources. Source files should never be suggested
+/// or moved as pairs of other sources.
+#[test]
+fn test_fix_moves_colocated() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "pkg/user_profile.go" => "",
+        "pkg/user_profile_service.go" => "",
+        "pkg/user_profile_test.go" => "",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .arg("check-file-pair")
+        .args(["--from", to_str!(dir_path.join("pkg"))])
+        .args(["--to", to_str!(dir_path.join("pkg"))])
+        .args(["--include", "**/*.go"])
+        .args(["--exclude", "**/*_test.go"])
+        .args(["--expect", "{to}/{relative_from}/{stem}_test.go"])
+        .args(["--fix-moves"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert!(!stdout.contains("Did you mean"));
+    assert!(!stdout.contains("Moving misplaced file"));
+    assert_eq!(
+        first_line(stderr),
+        "Error: There are 1 missing files. Use `--create-if-not-exists` to create them."
+    );
+    assert_eq!(
+        list_dir(dir_path),
+        &[
+            "pkg/user_profile.go",
+            "pkg/user_profile_service.go",
+            "pkg/user_profile_test.go",
+        ]
+    );
+    Ok(())
+}
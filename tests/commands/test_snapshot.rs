@@ -0,0 +1,178 @@
+use std::fs::{read_to_string, remove_file, rename, write};
+
+use anyhow::Result;
+use insta::assert_snapshot;
+use sugars::hmap;
+
+use crate::{helpers::{get_cmd, get_temp_dir, normalize_console_output, parse_output},
+            to_str};
+
+/// Test saving a snapshot. The manifest records the options and the hash of each file.
+#[test]
+fn test_save() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+        "target/subdir/file2.txt" => "Content of file 2",
+        "target/file3.log" => "Content of file 3",
+    });
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .args(["snapshot", "save"])
+        .args(["--target", "target"])
+        .args(["--exclude", "**/*.log"])
+        .args(["--out", "manifest.json"])
+        .assert();
+
+    // Assert
+    let result = assert.success().code(0);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(stderr, "");
+    assert_snapshot!(read_to_string(dir_path.join("manifest.json"))?);
+    Ok(())
+}
+
+/// Test verifying an unchanged target against the snapshot.
+#[test]
+fn test_verify_no_changes() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+        "target/subdir/file2.txt" => "Content of file 2",
+    });
+    let dir_path = temp_dir.path();
+    get_cmd()
+        .current_dir(dir_path)
+        .args(["snapshot", "save"])
+        .args(["--target", "target"])
+        .args(["--out", "manifest.json"])
+        .assert()
+        .success();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .args(["snapshot", "verify", "manifest.json"])
+        .assert();
+
+    // Assert
+    let result = assert.success().code(0);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stdout,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    assert_eq!(stderr, "");
+    Ok(())
+}
+
+/// Test verifying a target changed after the snapshot. Changes are reported per file.
+#[test]
+fn test_verify_changes() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+        "target/subdir/file2.txt" => "Content of file 2",
+        "target/file3.txt" => "Content of file 3",
+    });
+    let dir_path = temp_dir.path();
+    get_cmd()
+        .current_dir(dir_path)
+        .args(["snapshot", "save"])
+        .args(["--target", "target"])
+        .args(["--out", "manifest.json"])
+        .assert()
+        .success();
+    write(dir_path.join("target/file1.txt"), "Modified content")?;
+    remove_file(dir_path.join("target/subdir/file2.txt"))?;
+    write(dir_path.join("target/file4.txt"), "Content of file 4")?;
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .args(["snapshot", "verify", "manifest.json"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (_, stderr) = parse_output(result.get_output());
+    assert_snapshot!(normalize_console_output(
+        stderr,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    Ok(())
+}
+
+/// Test verifying a target in another location, as if the snapshot was taken elsewhere.
+#[test]
+fn test_verify_target() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {
+        "target/file1.txt" => "Content of file 1",
+        "target/subdir/file2.txt" => "Content of file 2",
+    });
+    let dir_path = temp_dir.path();
+    get_cmd()
+        .current_dir(dir_path)
+        .args(["snapshot", "save"])
+        .args(["--target", "target"])
+        .args(["--out", "manifest.json"])
+        .assert()
+        .success();
+    rename(dir_path.join("target"), dir_path.join("moved"))?;
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .args(["snapshot", "verify", "manifest.json"])
+        .args(["--target", "moved"])
+        .assert();
+
+    // Assert
+    assert.success().code(0);
+    Ok(())
+}
+
+/// Test verifying against a manifest which does not exist. It should exit with error.
+#[test]
+fn test_verify_nonexistent_manifest() -> Result<()> {
+    // Arrange
+    let temp_dir = get_temp_dir(hmap! {});
+    let dir_path = temp_dir.path();
+
+    // Act
+    let mut cmd = get_cmd();
+    let assert = cmd
+        .current_dir(dir_path)
+        .args(["snapshot", "verify", "manifest.json"])
+        .assert();
+
+    // Assert
+    let result = assert.failure().code(1);
+    let (stdout, stderr) = parse_output(result.get_output());
+    assert_eq!(stdout, "");
+    assert_snapshot!(normalize_console_output(
+        stderr,
+        hmap! {
+            to_str!(dir_path) => "<temp_dir>"
+        }
+    ));
+    Ok(())
+}